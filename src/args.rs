@@ -0,0 +1,556 @@
+use syn::ext::IdentExt;
+use syn::parse::{Parse, ParseStream};
+use syn::{Ident, Token};
+
+/// The arguments of an operator attribute, e.g. `#[opimps::impl_ops(Add; strict)]`.
+///
+/// The trait path comes first, optionally followed by `;` or `,` and a comma
+/// separated list of options.
+#[derive(Clone)]
+pub(crate) struct OpArgs {
+    pub trait_path: syn::TypePath,
+    /// The further traits to implement from the same function, e.g. `Sub` in `impl_ops(Add, Sub)`.
+    pub more_traits: Vec<syn::TypePath>,
+    /// Reject source functions whose visibility would be silently ignored.
+    pub strict: bool,
+    /// Mark the permutations with a borrowed operand `#[inline]`.
+    pub inline_refs: bool,
+    /// Implement the permutations with a borrowed operand by cloning it.
+    pub clone_refs: bool,
+    /// Implement the permutations with an owned operand by borrowing it.
+    pub deref_operands: bool,
+    /// Repeat the documentation on every permutation, only running its examples once.
+    pub doc_all: bool,
+    /// A name for `Self::Output` within the body.
+    pub out_alias: Option<Ident>,
+    /// Generate a test asserting that the permutations agree.
+    pub gen_test: bool,
+    /// The operands of the generated test.
+    pub sample: Option<syn::Expr>,
+    /// The `rhs` of the generated test, when it differs from the `sample`.
+    pub rhs_sample: Option<syn::Expr>,
+    /// The closure signature of a closure `rhs`, e.g. `rhs_fn = "Fn(i32) -> i32"`.
+    pub rhs_fn: Option<syn::TypeParamBound>,
+    /// The field of a newtype that the permutations with a borrowed operand project.
+    pub newtype: Option<syn::Member>,
+    /// The message to panic with when the `Result` returned by the body is an `Err`.
+    pub panics_on_err: Option<syn::LitStr>,
+    /// How the `rhs` type is written in the implemented trait when it's the type of `self`.
+    pub rhs_form: RhsForm,
+    /// The `Vec` field that `rhs` is pushed onto, generating the body.
+    pub push_field: Option<syn::Member>,
+    /// Implement a trait without an associated `Output`, returning the type as is.
+    pub no_output: bool,
+    /// The only permutations to generate, all of them by default.
+    pub only: Option<Vec<Permutation>>,
+    /// The permutations not to generate.
+    pub skip: Vec<Permutation>,
+    /// Also implement the operator with the operands swapped.
+    pub commutative: bool,
+    /// The `rhs` types to implement the operator for instead of the declared one.
+    pub rhs_types: Vec<syn::Type>,
+    /// Also implement the operator for a mutably borrowed `self`.
+    pub with_mut_lhs: bool,
+    /// The name of the associated output type, `Output` by default.
+    pub output: Option<Ident>,
+    /// Print the expansion while compiling, to debug it.
+    pub emit_debug: bool,
+    /// Only implement the owned primitive `rhs` of an assignment, without the borrowed one.
+    pub prim: bool,
+}
+
+impl OpArgs {
+    /// Whether the permutation is one of those to generate.
+    pub fn selects(&self, permutation: Permutation) -> bool {
+        let listed = match &self.only {
+            Some(only) => only.contains(&permutation),
+            None => true
+        };
+
+        listed && !self.skip.contains(&permutation)
+    }
+
+    /// The name of the associated output type of the trait.
+    pub fn output_name(&self) -> Ident {
+        self.output.clone().unwrap_or_else(|| Ident::new("Output", proc_macro2::Span::call_site()))
+    }
+}
+
+/// A permutation of owned and borrowed operands, named after `self` and then `rhs`.
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum Permutation {
+    OwnedOwned,
+    RefOwned,
+    OwnedRef,
+    RefRef,
+}
+
+impl Permutation {
+    pub fn of(lhs_ref: bool, rhs_ref: bool) -> Self {
+        match (lhs_ref, rhs_ref) {
+            (false, false) => Permutation::OwnedOwned,
+            (true, false) => Permutation::RefOwned,
+            (false, true) => Permutation::OwnedRef,
+            (true, true) => Permutation::RefRef,
+        }
+    }
+
+    /// The permutation with the operands swapped, e.g. `owned_ref` for `ref_owned`.
+    pub fn swapped(self) -> Self {
+        match self {
+            Permutation::RefOwned => Permutation::OwnedRef,
+            Permutation::OwnedRef => Permutation::RefOwned,
+            permutation => permutation,
+        }
+    }
+
+    /// The name of the permutation in `only` and `skip`.
+    pub fn ident(self) -> Ident {
+        let name = match self {
+            Permutation::OwnedOwned => "owned_owned",
+            Permutation::RefOwned => "ref_owned",
+            Permutation::OwnedRef => "owned_ref",
+            Permutation::RefRef => "ref_ref",
+        };
+
+        Ident::new(name, proc_macro2::Span::call_site())
+    }
+}
+
+/// How the `rhs` type is written in the implemented trait, e.g. `Add`, `Add<T>` or `Add<Self>`.
+#[derive(Clone)]
+pub(crate) enum RhsForm {
+    /// Written as is, e.g. `Add<T>`.
+    Concrete,
+    /// Left to the default of the trait, e.g. `Add`.
+    Omitted,
+    /// Written as `Self`, e.g. `Add<Self>`.
+    SelfType,
+}
+
+impl Parse for OpArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let trait_path = input.parse()?;
+        let more_traits = parse_more_traits(input)?;
+        let rhs_types = parse_primitive_types(input)?;
+
+        let mut args = OpArgs {
+            trait_path,
+            more_traits,
+            strict: false,
+            inline_refs: false,
+            clone_refs: false,
+            deref_operands: false,
+            doc_all: false,
+            out_alias: None,
+            gen_test: false,
+            sample: None,
+            rhs_sample: None,
+            rhs_fn: None,
+            newtype: None,
+            panics_on_err: None,
+            rhs_form: RhsForm::Concrete,
+            push_field: None,
+            no_output: false,
+            only: None,
+            skip: Vec::new(),
+            commutative: false,
+            rhs_types,
+            with_mut_lhs: false,
+            output: None,
+            emit_debug: false,
+            prim: false,
+        };
+
+        parse_options(input, |option, input| {
+            match option.to_string().as_str() {
+                "strict" => args.strict = true,
+                "inline_refs" => args.inline_refs = true,
+                "clone_refs" => args.clone_refs = true,
+                "deref_operands" => args.deref_operands = true,
+                "doc_all" => args.doc_all = true,
+                "out_alias" => {
+                    input.parse::<Token![=]>()?;
+                    args.out_alias = Some(input.parse()?);
+                }
+                "gen_test" => args.gen_test = true,
+                "sample" => {
+                    input.parse::<Token![=]>()?;
+                    args.sample = Some(input.parse()?);
+                }
+                "rhs_sample" => {
+                    input.parse::<Token![=]>()?;
+                    args.rhs_sample = Some(input.parse()?);
+                }
+                "rhs_fn" => {
+                    input.parse::<Token![=]>()?;
+                    args.rhs_fn = Some(input.parse::<syn::LitStr>()?.parse()?);
+                }
+                "newtype" => {
+                    input.parse::<Token![=]>()?;
+                    args.newtype = Some(input.parse()?);
+                }
+                "panics_on_err" => {
+                    input.parse::<Token![=]>()?;
+                    args.panics_on_err = Some(input.parse()?);
+                }
+                "no_output" => args.no_output = true,
+                "only" => args.only = Some(parse_permutations(input)?),
+                "skip" => args.skip = parse_permutations(input)?,
+                "commutative" => args.commutative = true,
+                "with_mut_lhs" => args.with_mut_lhs = true,
+                "emit_debug" => args.emit_debug = true,
+                "prim" => args.prim = true,
+                "output" => {
+                    input.parse::<Token![=]>()?;
+                    args.output = Some(input.parse()?);
+                }
+                "rhs_types" => {
+                    if !args.rhs_types.is_empty() {
+                        return Err(syn::Error::new(option.span(), "The `rhs` types are already listed."));
+                    }
+
+                    let content;
+                    syn::parenthesized!(content in input);
+                    args.rhs_types = content.parse_terminated(syn::Type::parse, Token![,])?.into_iter().collect();
+                }
+                "push_field" => {
+                    input.parse::<Token![=]>()?;
+                    args.push_field = Some(input.parse()?);
+                }
+                "rhs_form" => {
+                    input.parse::<Token![=]>()?;
+                    let form = Ident::parse_any(input)?;
+
+                    args.rhs_form = match form.to_string().as_str() {
+                        "concrete" => RhsForm::Concrete,
+                        "omitted" => RhsForm::Omitted,
+                        "self" => RhsForm::SelfType,
+                        _ => {
+                            return Err(syn::Error::new(form.span(), "Expected `concrete`, `omitted` or `self`."));
+                        }
+                    };
+                }
+                _ => return Err(unknown_option(option))
+            }
+
+            Ok(())
+        })?;
+
+        Ok(args)
+    }
+}
+
+/// The arguments of `impl_vec_ops`, e.g. `#[opimps::impl_vec_ops(Add, field = 0, on_mismatch = panic)]`.
+pub(crate) struct VecOpArgs {
+    pub trait_path: syn::TypePath,
+    /// The `Vec` field of the structure, `0` by default.
+    pub field: syn::Member,
+    pub on_mismatch: OnMismatch,
+}
+
+/// What to do when the lengths of the operands differ.
+pub(crate) enum OnMismatch {
+    /// Panic, making the output the structure itself.
+    Panic,
+    /// Return `Err`, making the output `Result<Self, E>` where `E: From<(usize, usize)>`.
+    Result(Box<syn::Type>),
+}
+
+impl Parse for VecOpArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let trait_path = input.parse()?;
+
+        let mut args = VecOpArgs {
+            trait_path,
+            field: syn::parse_quote!(0),
+            on_mismatch: OnMismatch::Panic,
+        };
+
+        parse_options(input, |option, input| {
+            input.parse::<Token![=]>()?;
+
+            match option.to_string().as_str() {
+                "field" => args.field = input.parse()?,
+                "on_mismatch" => {
+                    let policy: Ident = input.parse()?;
+
+                    args.on_mismatch = match policy.to_string().as_str() {
+                        "panic" => OnMismatch::Panic,
+                        "result" => {
+                            let content;
+                            syn::parenthesized!(content in input);
+                            OnMismatch::Result(Box::new(content.parse()?))
+                        }
+                        _ => {
+                            return Err(syn::Error::new(policy.span(), "Expected `panic` or `result(ErrorType)`."));
+                        }
+                    };
+                }
+                _ => return Err(unknown_option(option))
+            }
+
+            Ok(())
+        })?;
+
+        Ok(args)
+    }
+}
+
+/// The arguments of `impl_scalar_ops`, e.g. `#[opimps::impl_scalar_ops(Add, wrapper = Secs, field = 0, prim = f64)]`.
+pub(crate) struct ScalarOpArgs {
+    pub trait_path: syn::TypePath,
+    /// The newtype, which must be the structure itself.
+    pub wrapper: Option<Ident>,
+    /// The wrapped field of the newtype, `0` by default.
+    pub field: syn::Member,
+    /// The primitive type, the type of the field by default.
+    pub prim: Option<syn::Type>,
+}
+
+impl Parse for ScalarOpArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let trait_path = input.parse()?;
+
+        let mut args = ScalarOpArgs {
+            trait_path,
+            wrapper: None,
+            field: syn::parse_quote!(0),
+            prim: None,
+        };
+
+        parse_options(input, |option, input| {
+            input.parse::<Token![=]>()?;
+
+            match option.to_string().as_str() {
+                "wrapper" => args.wrapper = Some(input.parse()?),
+                "field" => args.field = input.parse()?,
+                "prim" => args.prim = Some(input.parse()?),
+                _ => return Err(unknown_option(option))
+            }
+
+            Ok(())
+        })?;
+
+        Ok(args)
+    }
+}
+
+/// The arguments of `impl_op_ref`, e.g. `#[opimps::impl_op_ref(Row; lifetime = 'a, borrow = self)]`.
+pub(crate) struct RefOpArgs {
+    pub trait_path: syn::TypePath,
+    /// The lifetime of the returned reference, `'a` by default.
+    pub lifetime: syn::Lifetime,
+    /// The operand the returned reference borrows from, `self` by default.
+    pub borrow: Borrow,
+}
+
+/// The operand that a returned reference borrows from.
+pub(crate) enum Borrow {
+    Lhs,
+    Rhs,
+}
+
+impl Parse for RefOpArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let trait_path = input.parse()?;
+
+        let mut args = RefOpArgs {
+            trait_path,
+            lifetime: syn::parse_quote!('a),
+            borrow: Borrow::Lhs,
+        };
+
+        parse_options(input, |option, input| {
+            input.parse::<Token![=]>()?;
+
+            match option.to_string().as_str() {
+                "lifetime" => args.lifetime = input.parse()?,
+                "borrow" => {
+                    let operand = Ident::parse_any(input)?;
+
+                    args.borrow = match operand.to_string().as_str() {
+                        "self" => Borrow::Lhs,
+                        "rhs" => Borrow::Rhs,
+                        _ => {
+                            return Err(syn::Error::new(operand.span(), "Expected `self` or `rhs`."));
+                        }
+                    };
+                }
+                _ => return Err(unknown_option(option))
+            }
+
+            Ok(())
+        })?;
+
+        Ok(args)
+    }
+}
+
+/// Parses the trait path of a macro without options, allowing a trailing comma, e.g.
+/// `impl_cmp_ops(PartialEq,)`.
+pub(crate) fn parse_trait_path(input: ParseStream) -> syn::Result<syn::TypePath> {
+    let trait_path = input.parse()?;
+    parse_options(input, |option, _| Err(unknown_option(option)))?;
+
+    Ok(trait_path)
+}
+
+/// Parses the `;` or `,` separating the trait path from the options, followed by the
+/// comma separated options. The name of each option is passed to `parse_option`, which
+/// parses the rest of that option.
+fn parse_options(
+    input: ParseStream,
+    mut parse_option: impl FnMut(&Ident, ParseStream) -> syn::Result<()>
+) -> syn::Result<()> {
+    if input.is_empty() {
+        return Ok(());
+    }
+
+    if input.peek(Token![;]) {
+        input.parse::<Token![;]>()?;
+    } else {
+        input.parse::<Token![,]>()?;
+    }
+
+    while !input.is_empty() {
+        let option: Ident = input.parse()?;
+        parse_option(&option, input)?;
+
+        if input.is_empty() {
+            break;
+        }
+
+        input.parse::<Token![,]>()?;
+    }
+
+    Ok(())
+}
+
+/// Parses the further trait paths that may follow the first one, e.g. `Add, Sub`, up to the
+/// options. A trait path is told apart from an option by being qualified or capitalized,
+/// as the options are all snake case.
+fn parse_more_traits(input: ParseStream) -> syn::Result<Vec<syn::TypePath>> {
+    let mut traits = Vec::new();
+
+    while input.peek(Token![,]) {
+        let fork = input.fork();
+        fork.parse::<Token![,]>()?;
+
+        let is_trait = fork.parse::<syn::TypePath>().is_ok_and(|path| {
+            path.qself.is_some()
+                || path.path.leading_colon.is_some()
+                || path.path.segments.len() > 1
+                || path.path.segments[0].ident.to_string().starts_with(|c: char| c.is_uppercase())
+        });
+
+        if !is_trait {
+            break;
+        }
+
+        input.parse::<Token![,]>()?;
+        traits.push(input.parse()?);
+    }
+
+    Ok(traits)
+}
+
+/// Parses the list of primitive types that may follow the trait path, e.g. `Shl; u8, u16`,
+/// up to the options. The list is told apart from the options by its first type being a
+/// primitive type, as none of the options is named like one.
+fn parse_primitive_types(input: ParseStream) -> syn::Result<Vec<syn::Type>> {
+    let fork = input.fork();
+    let starts_list = fork.parse::<Token![;]>().is_ok()
+        && fork.parse::<syn::Type>().is_ok_and(|ty| crate::is_primitive(&ty));
+
+    if !starts_list {
+        return Ok(Vec::new());
+    }
+
+    input.parse::<Token![;]>()?;
+
+    let mut types = Vec::new();
+
+    while !input.is_empty() && !input.peek(Token![;]) {
+        types.push(input.parse()?);
+
+        if input.is_empty() || input.peek(Token![;]) {
+            break;
+        }
+
+        input.parse::<Token![,]>()?;
+    }
+
+    Ok(types)
+}
+
+/// Parses a parenthesized list of permutations, e.g. `(ref_ref, owned_owned)`.
+fn parse_permutations(input: ParseStream) -> syn::Result<Vec<Permutation>> {
+    let content;
+    syn::parenthesized!(content in input);
+
+    content.parse_terminated(Ident::parse, Token![,])?
+        .into_iter()
+        .map(|name| match name.to_string().as_str() {
+            "owned_owned" => Ok(Permutation::OwnedOwned),
+            "ref_owned" => Ok(Permutation::RefOwned),
+            "owned_ref" => Ok(Permutation::OwnedRef),
+            "ref_ref" => Ok(Permutation::RefRef),
+            _ => Err(syn::Error::new(name.span(), "Expected `owned_owned`, `ref_owned`, `owned_ref` or `ref_ref`."))
+        })
+        .collect()
+}
+
+fn unknown_option(option: &Ident) -> syn::Error {
+    syn::Error::new(option.span(), format!("Unknown option `{}`.", option))
+}
+
+/// The arguments of `impl_iter_sum` and `impl_iter_product`, e.g. `#[opimps::impl_iter_sum(borrowed)]`.
+pub(crate) struct IterArgs {
+    /// Implement the trait for borrowed items as well, cloning them, when the item type isn't generic.
+    pub borrowed: bool,
+}
+
+impl Parse for IterArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut args = IterArgs { borrowed: false };
+
+        for option in input.parse_terminated(Ident::parse, Token![,])? {
+            match option.to_string().as_str() {
+                "borrowed" => args.borrowed = true,
+                _ => return Err(unknown_option(&option))
+            }
+        }
+
+        Ok(args)
+    }
+}
+
+/// The arguments of `impl_option_ops`, e.g. `#[opimps::impl_option_ops(Merge, inner_merge = combine)]`.
+pub(crate) struct OptionOpArgs {
+    pub trait_path: syn::TypePath,
+    /// The function combining the values when both operands are `Some`.
+    pub inner_merge: syn::Path,
+}
+
+impl Parse for OptionOpArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let trait_path = input.parse()?;
+        let mut inner_merge = None;
+
+        parse_options(input, |option, input| {
+            input.parse::<Token![=]>()?;
+
+            match option.to_string().as_str() {
+                "inner_merge" => inner_merge = Some(input.parse()?),
+                _ => return Err(unknown_option(option))
+            }
+
+            Ok(())
+        })?;
+
+        let inner_merge = inner_merge.ok_or_else(|| input.error("Expected `inner_merge = function`."))?;
+
+        Ok(OptionOpArgs { trait_path, inner_merge })
+    }
+}
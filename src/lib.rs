@@ -7,6 +7,14 @@ use quote::{ quote, ToTokens };
 
 use syn::{Attribute, parse_macro_input};
 
+mod args;
+use args::{Borrow, IterArgs, OnMismatch, OpArgs, OptionOpArgs, Permutation, RefOpArgs, RhsForm, ScalarOpArgs, VecOpArgs};
+
+mod ops;
+
+#[cfg(test)]
+mod tests;
+
 /// Implements the unary operators for the specified type.
 /// 
 /// ```
@@ -28,49 +36,59 @@ use syn::{Attribute, parse_macro_input};
 /// ```
 #[proc_macro_attribute]
 pub fn impl_uni_op(attr: TokenStream, item: TokenStream) -> TokenStream {
-    let trait_path = parse_macro_input!(attr as syn::TypePath);
-    let fn_item = parse_macro_input!(item as syn::ItemFn);
-    let fn_name = fn_item.sig.ident;
-    let fn_generics = fn_item.sig.generics;
-    let mut fn_args = fn_item.sig.inputs.into_iter();
+    let args = parse_macro_input!(attr as OpArgs);
+    let fn_item = parse_macro_input!(item with parse_operator_fn);
 
-    const INSUFFICIENT_ARGS_MSG: &str = "Function definition requires an argument (self: T).";
+    if let Err(e) = check_signature(&args, &fn_item) {
+        return e.to_compile_error().into();
+    }
 
-    let lhs = fn_args.next().expect(INSUFFICIENT_ARGS_MSG);
+    if let Err(e) = check_single_trait(&args) {
+        return e.to_compile_error().into();
+    }
 
-    let attrs = fn_item.attrs;
+    let lhs = match unary_param(&fn_item.sig) {
+        Ok(param) => param,
+        Err(e) => return e.to_compile_error().into()
+    };
 
-    let lhs = match lhs {
-        syn::FnArg::Receiver(e) => e,
-        _ => { panic!("Error processing first argument.") }
+    let fn_type = match output_type(&fn_item.sig) {
+        Ok(output) => output,
+        Err(e) => return e.to_compile_error().into()
     };
 
-    let mut other_tkns = proc_macro2::TokenStream::new();
-    
-    attrs.into_iter().fold(
-        &mut other_tkns,
-        |tkn, attr|{ tkn.extend(attr.to_token_stream()); tkn }
-    );
+    let output = args.output_name();
+    let trait_path = args.trait_path;
+    let fn_name = trait_method(&trait_path, fn_item.sig.ident);
+    let fn_generics = fn_item.sig.generics;
+
+    let attrs = fn_item.attrs;
+
+    // The `cfg` attributes gate the implementation as a whole, while the others go on the method.
+    let (cfg_tkns, other_tkns) = extract_cfgs(&attrs);
 
     let lhs_type = &lhs.ty;
 
-    let fn_body = fn_item.block;
-    
-    let fn_type = match fn_item.sig.output {
-        syn::ReturnType::Type(_, typ) => typ,
-        _ => { panic!("Function must contain a return type.") }
+    let fn_body = match alias_output(args.out_alias.as_ref(), &output, fn_item.block) {
+        Ok(block) => block,
+        Err(e) => return e.to_compile_error().into()
     };
+    
+    let (impl_generics, _, where_clause) = fn_generics.split_for_impl();
 
-    let where_clause = &fn_generics.where_clause;
-
-    quote! {
-        impl #fn_generics #trait_path for #lhs_type #where_clause {
-            type Output = #fn_type;
+    let token = quote! {
+        #cfg_tkns
+        #[automatically_derived]
+        impl #impl_generics #trait_path for #lhs_type #where_clause {
+            type #output = #fn_type;
             #other_tkns
-            fn #fn_name (self) -> Self::Output 
+            fn #fn_name (self) -> Self::#output 
                 #fn_body
         }
-    }.into()
+    };
+
+    debug_expansion(args.emit_debug, &fn_name, &token);
+    token.into()
 }
 
 /// Implements the unary operators for the specified type.
@@ -95,55 +113,150 @@ pub fn impl_uni_op(attr: TokenStream, item: TokenStream) -> TokenStream {
 /// let b = !a;
 /// assert_eq!(true, b);
 /// ```
+///
+/// The same body is used for both the owned (`T`) and borrowed (`&T`) implementations,
+/// so it must compile for both. Moving a field out of `self` only works for the owned
+/// implementation; the borrowed implementation has to clone it instead, which requires
+/// the field's type to be `Clone`.
+///
+/// ```compile_fail
+/// trait IntoInner {
+///     type Output;
+///     fn into_inner(self) -> Self::Output;
+/// }
+///
+/// struct Wrapper(String);
+///
+/// // Error: cannot move out of `self.0` which is behind a shared reference.
+/// #[opimps::impl_uni_ops(IntoInner)]
+/// fn into_inner(self: Wrapper) -> String {
+///     self.0
+/// }
+/// ```
 #[proc_macro_attribute]
 pub fn impl_uni_ops(attr: TokenStream, item: TokenStream) -> TokenStream {
-    let trait_path = parse_macro_input!(attr as syn::TypePath);
-    let fn_item = parse_macro_input!(item as syn::ItemFn);
-    let fn_name = fn_item.sig.ident;
-    let fn_generics = fn_item.sig.generics;
+    let args = parse_macro_input!(attr as OpArgs);
+    let fn_item = parse_macro_input!(item with parse_operator_fn);
 
-    let mut fn_args = fn_item.sig.inputs.into_iter();
-    const INSUFFICIENT_ARGS_MSG: &str = "Function definition requires an argument (self: T).";
+    if let Err(e) = check_signature(&args, &fn_item) {
+        return e.to_compile_error().into();
+    }
 
-    let lhs = fn_args.next().expect(INSUFFICIENT_ARGS_MSG);
+    if let Err(e) = check_single_trait(&args) {
+        return e.to_compile_error().into();
+    }
 
-    let attrs = fn_item.attrs;
+    let lhs = match unary_param(&fn_item.sig) {
+        Ok(param) => param,
+        Err(e) => return e.to_compile_error().into()
+    };
 
-    let lhs = match lhs {
-        syn::FnArg::Receiver(e) => e,
-        _ => { panic!("Error processing first argument.")}
+    let fn_output = match output_type(&fn_item.sig) {
+        Ok(output) => output,
+        Err(e) => return e.to_compile_error().into()
     };
+    let fn_output = output_of_self(fn_output, &lhs.ty);
+
+    let output = args.output_name();
+    let forwarded = forwarded_options(&args);
+    let trait_path = args.trait_path;
+    let fn_name = trait_method(&trait_path, fn_item.sig.ident);
+    let fn_generics = fn_item.sig.generics;
+
+    let attrs = fn_item.attrs;
 
     let (comments, other_tkns) = extract_comments(&attrs);
 
     let lhs_pat = &lhs.self_token;
     let lhs_type = &lhs.ty;
 
-    let fn_body = fn_item.block;
-    
-    let fn_output = match fn_item.sig.output {
-        syn::ReturnType::Type(_, typ) => typ,
-        _ => { panic!("Function must contain a return type.") }
+    let fn_body = match alias_output(args.out_alias.as_ref(), &output, fn_item.block) {
+        Ok(block) => block,
+        Err(e) => return e.to_compile_error().into()
     };
-
+    
     let where_clause = &fn_generics.where_clause;
 
-    let token = quote! {
+    let owned = quote! {
         #comments
         #other_tkns
-        #[opimps::impl_uni_op(#trait_path)]
+        #[opimps::impl_uni_op(#trait_path #forwarded)]
         fn #fn_name #fn_generics (#lhs) -> #fn_output #where_clause
             #fn_body
+    };
+
+    // A `self` declared as a reference, e.g. `&mut T` for an operator mutating its operand,
+    // is implemented as is rather than borrowed again, and so is a boxed `self`.
+    if matches!(lhs_type.as_ref(), syn::Type::Reference(_)) || is_boxed(lhs_type) {
+        debug_expansion(args.emit_debug, &fn_name, &owned);
+        return TokenStream::from(owned);
+    }
+
+    let token = quote! {
+        #owned
 
         #other_tkns
-        #[opimps::impl_uni_op(#trait_path)]
+        #[opimps::impl_uni_op(#trait_path #forwarded)]
         fn #fn_name #fn_generics (#lhs_pat: &#lhs_type) -> #fn_output #where_clause
             #fn_body
     };
 
+    debug_expansion(args.emit_debug, &fn_name, &token);
     TokenStream::from(token)
 }
 
+/// Implements `Neg` for both owned and borrowed data, as `impl_uni_ops(Neg)` would.
+///
+/// ```
+/// #[derive(Debug, PartialEq)]
+/// pub struct Offset(i32);
+///
+/// #[opimps::impl_neg]
+/// fn neg(self: Offset) -> Offset {
+///     Offset(-self.0)
+/// }
+///
+/// let offset = Offset(3);
+///
+/// assert_eq!(Offset(-3), -&offset);
+/// assert_eq!(Offset(-3), -offset);
+/// ```
+#[proc_macro_attribute]
+pub fn impl_neg(attr: TokenStream, item: TokenStream) -> TokenStream {
+    TokenStream::from(uni_ops_of(quote! { ::core::ops::Neg }, attr.into(), item.into()))
+}
+
+/// Implements `Not` for both owned and borrowed data, as `impl_uni_ops(Not)` would.
+///
+/// ```
+/// #[derive(Debug, PartialEq)]
+/// pub struct Switch(bool);
+///
+/// #[opimps::impl_not]
+/// fn not(self: Switch) -> Switch {
+///     Switch(!self.0)
+/// }
+///
+/// let on = Switch(true);
+///
+/// assert_eq!(Switch(false), !&on);
+/// assert_eq!(Switch(false), !on);
+/// ```
+#[proc_macro_attribute]
+pub fn impl_not(attr: TokenStream, item: TokenStream) -> TokenStream {
+    TokenStream::from(uni_ops_of(quote! { ::core::ops::Not }, attr.into(), item.into()))
+}
+
+/// Forwards to `impl_uni_ops` for the given trait, with the options of the attribute.
+fn uni_ops_of(trait_path: proc_macro2::TokenStream, attr: proc_macro2::TokenStream, item: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    let options = (!attr.is_empty()).then(|| quote! { ; #attr });
+
+    quote! {
+        #[opimps::impl_uni_ops(#trait_path #options)]
+        #item
+    }
+}
+
 /// The direct implementation for binary operators. This is used when you only need one implementation.
 /// 
 /// ```
@@ -178,151 +291,263 @@ pub fn impl_uni_ops(attr: TokenStream, item: TokenStream) -> TokenStream {
 /// 
 /// assert_eq!(28, &a * b);
 /// ```
+///
+/// The output becomes the associated `Output` type, so it can't be an `impl Trait`.
+///
+/// ```compile_fail
+/// pub struct Scale(i32);
+///
+/// // Error: The output can't be an `impl Trait`, ...
+/// #[opimps::impl_op(std::ops::Mul)]
+/// fn mul(self: Scale, rhs: Scale) -> impl Fn(i32) -> i32 {
+///     move |x| x * self.0 * rhs.0
+/// }
+/// ```
+///
+/// A function taking `self: &mut T` and returning `&mut Self` implements a chainable
+/// operator for `T`, where the trait method is `fn op(&mut self, rhs: Rhs) -> &mut Self`.
+///
+/// ```
+/// pub trait Accumulate<Rhs> {
+///     fn accumulate(&mut self, rhs: Rhs) -> &mut Self;
+/// }
+///
+/// pub struct Total(f64);
+///
+/// #[opimps::impl_op(Accumulate)]
+/// fn accumulate(self: &mut Total, rhs: f64) -> &mut Self {
+///     self.0 += rhs;
+///     self
+/// }
+///
+/// let mut total = Total(1.0);
+/// total.accumulate(2.0).accumulate(3.0);
+///
+/// assert_eq!(6.0, total.0);
+/// ```
+///
+/// The operand types can't be macro invocations, as opimps can't inspect the type a macro
+/// expands to when it checks for references and same-type operands.
+///
+/// ```compile_fail
+/// macro_rules! scale {
+///     () => { Scale };
+/// }
+///
+/// pub struct Scale(i32);
+///
+/// // Error: Operand types must be concrete types, not macro invocations.
+/// #[opimps::impl_op(std::ops::Mul)]
+/// fn mul(self: scale!(), rhs: i32) -> i32 {
+///     self.0 * rhs
+/// }
+/// ```
+///
+/// Both operands are required, even when the body doesn't use `rhs`.
+///
+/// ```compile_fail
+/// pub struct TestObj {
+///     val: i32
+/// }
+///
+/// // Error: Requires two arguments (self: T1, rhs: T2).
+/// #[opimps::impl_op(std::ops::Neg)]
+/// fn neg(self: TestObj) -> i32 {
+///     return -self.val;
+/// }
+/// ```
 #[proc_macro_attribute]
 pub fn impl_op(attr: TokenStream, item: TokenStream) -> TokenStream {
-    let trait_path = parse_macro_input!(attr as syn::TypePath);
-    let fn_item = parse_macro_input!(item as syn::ItemFn);
-    let fn_name = fn_item.sig.ident;
-    let fn_generics = fn_item.sig.generics;
-    let mut fn_args = fn_item.sig.inputs.into_iter();
-
-    const INSUFFICIENT_ARGS_MSG: &str = "Requires two arguments (self: T1, rhs: T2).";
+    let args = parse_macro_input!(attr as OpArgs);
+    let fn_item = parse_macro_input!(item with parse_operator_fn);
 
-    let lhs = fn_args.next().expect(INSUFFICIENT_ARGS_MSG);
-    let rhs = fn_args.next().expect(INSUFFICIENT_ARGS_MSG);
+    if let Err(e) = check_signature(&args, &fn_item) {
+        return e.to_compile_error().into();
+    }
 
-    let attrs = fn_item.attrs;
+    TokenStream::from(for_each_trait(args, fn_item, expand_op))
+}
 
-    let lhs = match lhs {
-        syn::FnArg::Receiver(e) => e,
-        _ => { panic!("Error processing first argument.")}
+fn expand_op(args: OpArgs, fn_item: syn::ItemFn) -> proc_macro2::TokenStream {
+    let (mut lhs, rhs) = match binary_params(&fn_item.sig) {
+        Ok(params) => params,
+        Err(e) => return e.to_compile_error()
     };
 
-    let rhs = match rhs {
-        syn::FnArg::Typed(e) => e,
-        _ => { panic!("Error processing second argument.")}
+    let mut fn_output = match binary_output_type(&fn_item.sig) {
+        Ok(output) => output,
+        Err(e) => return e.to_compile_error()
     };
 
-    let mut other_tkns = proc_macro2::TokenStream::new();
+    let output = args.output_name();
+    let trait_path = args.trait_path;
+    let fn_name = trait_method(&trait_path, fn_item.sig.ident);
+    let mut fn_generics = fn_item.sig.generics;
 
-    attrs.into_iter().fold(
-        &mut other_tkns,
-        |tkn, attr|{ tkn.extend(attr.to_token_stream()); tkn }
-    );
+    if fluent_receiver(&lhs.ty, &fn_output).is_none() {
+        if let Err(e) = tie_output_to_receiver(&mut lhs.ty, &mut fn_output, &mut fn_generics) {
+            return e.to_compile_error();
+        }
+    }
+
+    let attrs = fn_item.attrs;
+
+    // The `cfg` attributes gate the implementation as a whole, while the others go on the method.
+    let (cfg_tkns, other_tkns) = extract_cfgs(&attrs);
 
+    // The trait is implemented for the type of `self`, whether it's owned, `&T` or `&mut T`,
+    // so the method takes `self` by value, and is only `mut self` when declared as such.
+    let lhs_mut = &lhs.mutability;
     let lhs_type = &lhs.ty;
     let rhs_type = &rhs.ty;
 
-    let fn_body = fn_item.block;
-    
-    let fn_output = match fn_item.sig.output {
-        syn::ReturnType::Type(_, typ) => typ,
-        _ => { panic!("Function must contain a return type.") }
+    let fn_body = match alias_output(args.out_alias.as_ref(), &output, fn_item.block) {
+        Ok(block) => unwrap_or_panic(args.panics_on_err.as_ref(), block),
+        Err(e) => return e.to_compile_error()
     };
-
-    let where_clause = &fn_generics.where_clause;
     
-    let token = quote! {
-        impl #fn_generics #trait_path<#rhs_type> for #lhs_type #where_clause {
-            type Output = #fn_output;
-            #other_tkns
-            fn #fn_name (self, #rhs) -> Self::Output {
-                #fn_body
+    // Unlike the generics of the function, those of the implementation can't have defaults.
+    let (impl_generics, _, where_clause) = fn_generics.split_for_impl();
+
+    // A `&mut T` receiver returning `&mut Self` is a fluent, chainable operator. The trait
+    // is implemented for `T` itself and the method takes and returns `&mut self`.
+    if let Some(lhs_elem) = fluent_receiver(lhs_type, &fn_output) {
+        let trait_ref = if has_generic_args(&trait_path) {
+            quote! { #trait_path }
+        } else {
+            quote! { #trait_path<#rhs_type> }
+        };
+
+        let token = quote! {
+            #cfg_tkns
+            #[automatically_derived]
+            impl #impl_generics #trait_ref for #lhs_elem #where_clause {
+                #other_tkns
+                fn #fn_name (&mut self, #rhs) -> &mut Self
+                    #fn_body
             }
-        }
+        };
+
+        return token;
+    }
+    
+    // When both operands have the same type, the `rhs` type may be left to the default
+    // of the trait or written as `Self` instead. A trait path with generic arguments is
+    // written as is.
+    let trait_ref = match args.rhs_form {
+        _ if has_generic_args(&trait_path) => quote! { #trait_path },
+        RhsForm::Omitted if same_type(lhs_type, rhs_type) => quote! { #trait_path },
+        RhsForm::SelfType if same_type(lhs_type, rhs_type) => quote! { #trait_path<Self> },
+        _ => quote! { #trait_path<#rhs_type> }
     };
 
-    TokenStream::from(token)
+    // Traits without an `Output` fix the return type of their method themselves.
+    if args.no_output {
+        quote! {
+            #cfg_tkns
+            #[automatically_derived]
+            impl #impl_generics #trait_ref for #lhs_type #where_clause {
+                #other_tkns
+                fn #fn_name (#lhs_mut self, #rhs) -> #fn_output
+                    #fn_body
+            }
+        }
+    } else {
+        quote! {
+            #cfg_tkns
+            #[automatically_derived]
+            impl #impl_generics #trait_ref for #lhs_type #where_clause {
+                type #output = #fn_output;
+                #other_tkns
+                fn #fn_name (#lhs_mut self, #rhs) -> Self::#output
+                    #fn_body
+            }
+        }
+    }
 }
 
-/// Implements the permutations of owned and borrowed data.
-/// 
+/// Implements a binary operator whose output is a reference borrowed from one of the
+/// operands, such as projecting a row out of a matrix.
+///
+/// The `lifetime` option (`'a` by default) names the lifetime shared by the output and
+/// the operand selected with `borrow = self` (the default) or `borrow = rhs`. It is added
+/// to the generics and to the references if they are not already declared, generating
+/// `impl<'a> Trait<Rhs> for &'a T { type Output = &'a Out; ... }`.
+///
+/// Traits with a generic associated `Output` are not supported.
+///
 /// ```
-/// use std::ops::Mul;
+/// pub trait Row<Rhs> {
+///     type Output;
+///     fn row(self, rhs: Rhs) -> Self::Output;
+/// }
 ///
-/// pub struct ANumber {
-///     val: i32
+/// pub struct Matrix {
+///     cols: usize,
+///     data: Vec<f64>
 /// }
 ///
-/// #[opimps::impl_ops(Mul)] 
-/// fn mul(self: ANumber, rhs: i32) -> i32 {
-///     return self.val * rhs;
+/// #[opimps::impl_op_ref(Row; lifetime = 'a, borrow = self)]
+/// fn row(self: &Matrix, i: usize) -> &[f64] {
+///     &self.data[i * self.cols..(i + 1) * self.cols]
 /// }
-/// 
-/// let a = ANumber { val: 4 };
-/// let b = 7;
-/// 
-/// assert_eq!(28, &a * &b);
-/// assert_eq!(28, a * b);
+///
+/// let m = Matrix { cols: 2, data: vec![1.0, 2.0, 3.0, 4.0] };
+///
+/// assert_eq!(&[3.0, 4.0], m.row(1));
 /// ```
 #[proc_macro_attribute]
-pub fn impl_ops(attr: TokenStream, item: TokenStream) -> TokenStream {
-    let trait_path = parse_macro_input!(attr as syn::TypePath);
-    let fn_item = parse_macro_input!(item as syn::ItemFn);
-    let fn_name = fn_item.sig.ident;
-    let fn_generics= fn_item.sig.generics;
-    let mut fn_args = fn_item.sig.inputs.into_iter();
+pub fn impl_op_ref(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(attr as RefOpArgs);
+    let fn_item = parse_macro_input!(item with parse_operator_fn);
 
-    const INSUFFICIENT_ARGS_MSG: &str = "Requires two arguments (self: T1, rhs: T2).";
+    TokenStream::from(expand_op_ref(args, fn_item))
+}
 
-    let lhs = fn_args.next().expect(INSUFFICIENT_ARGS_MSG);
-    let rhs = fn_args.next().expect(INSUFFICIENT_ARGS_MSG);
-    
-    let lhs = match lhs {
-        syn::FnArg::Receiver(e) => e,
-        _ => { panic!("Error processing first argument.")}
-    };
+fn expand_op_ref(args: RefOpArgs, mut fn_item: syn::ItemFn) -> proc_macro2::TokenStream {
+    let trait_path = &args.trait_path;
+    let lifetime = &args.lifetime;
 
-    let rhs = match rhs {
-        syn::FnArg::Typed(e) => e,
-        _ => { panic!("Error processing second argument.")}
-    };
+    if let Err(e) = binary_params(&fn_item.sig) {
+        return e.to_compile_error();
+    }
 
-    let lhs_pat = &lhs.self_token;
-    let lhs_type = &lhs.ty;
-    let rhs_pat = &rhs.pat;
-    let rhs_type = &rhs.ty;
-    
-    let fn_body = fn_item.block;    
-    let fn_output = match fn_item.sig.output {
-        syn::ReturnType::Type(_, typ) => typ,
-        _ => { panic!("Function must contain a return type.") }
+    let borrowed = match args.borrow {
+        Borrow::Lhs => 0,
+        Borrow::Rhs => 1
     };
 
-    let attrs = fn_item.attrs;
-
-    let (comments, other_tkns) = extract_comments(&attrs);
+    let borrowed_type = match &mut fn_item.sig.inputs[borrowed] {
+        syn::FnArg::Receiver(lhs) => &mut lhs.ty,
+        syn::FnArg::Typed(rhs) => &mut rhs.ty
+    };
 
-    let where_clause = &fn_generics.where_clause;
+    if let Err(e) = set_reference_lifetime(borrowed_type, lifetime) {
+        return e.to_compile_error();
+    }
 
-    let token = quote!{
-        #comments
-        #other_tkns
-        #[opimps::impl_op(#trait_path)]
-        fn #fn_name #fn_generics (#lhs, #rhs) -> #fn_output #where_clause
-            #fn_body
+    let fn_output = match &mut fn_item.sig.output {
+        syn::ReturnType::Type(_, typ) => typ,
+        syn::ReturnType::Default => return missing_output(&fn_item.sig).to_compile_error()
+    };
 
-        #other_tkns
-        #[opimps::impl_op(#trait_path)]
-        fn #fn_name #fn_generics (#lhs_pat: &#lhs_type, #rhs_pat: &#rhs_type) -> #fn_output #where_clause
-            #fn_body
+    if let Err(e) = set_reference_lifetime(fn_output, lifetime) {
+        return e.to_compile_error();
+    }
 
-        #other_tkns
-        #[opimps::impl_op(#trait_path)]
-        fn #fn_name #fn_generics (#lhs_pat: #lhs_type, #rhs_pat: &#rhs_type) -> #fn_output #where_clause
-            #fn_body
+    let generics = &mut fn_item.sig.generics;
+    let declared = generics.lifetimes().any(|param| &param.lifetime == lifetime);
+    if !declared {
+        generics.params.insert(0, syn::GenericParam::Lifetime(syn::LifetimeParam::new(lifetime.clone())));
+    }
 
-        #other_tkns
+    quote! {
         #[opimps::impl_op(#trait_path)]
-        fn #fn_name #fn_generics (#lhs_pat: &#lhs_type, #rhs_pat: #rhs_type) -> #fn_output #where_clause
-            #fn_body
-    };
-    
-    TokenStream::from(token)
+        #fn_item
+    }
 }
 
-/// Implements the permutations of owned and borrowed data, with `rhs` being a 
-/// primitive value and `self` being a structure.
+/// Implements the permutations of owned and borrowed data.
 /// 
 /// ```
 /// use std::ops::Mul;
@@ -331,7 +556,7 @@ pub fn impl_ops(attr: TokenStream, item: TokenStream) -> TokenStream {
 ///     val: i32
 /// }
 ///
-/// #[opimps::impl_ops_rprim(Mul)] 
+/// #[opimps::impl_ops(Mul)] 
 /// fn mul(self: ANumber, rhs: i32) -> i32 {
 ///     return self.val * rhs;
 /// }
@@ -339,63 +564,660 @@ pub fn impl_ops(attr: TokenStream, item: TokenStream) -> TokenStream {
 /// let a = ANumber { val: 4 };
 /// let b = 7;
 /// 
-/// assert_eq!(28, &a * b);
+/// assert_eq!(28, &a * &b);
 /// assert_eq!(28, a * b);
 /// ```
+///
+/// The generated implementations inherit the visibility of the trait, so a `pub` on the
+/// function has no effect. The `strict` option turns this into an error.
+///
+/// ```compile_fail
+/// use std::ops::Mul;
+///
+/// pub struct ANumber {
+///     val: i32
+/// }
+///
+/// #[opimps::impl_ops(Mul; strict)]
+/// pub fn mul(self: ANumber, rhs: i32) -> i32 {
+///     return self.val * rhs;
+/// }
+/// ```
+///
+/// As with `impl_op`, the operand types can't be macro invocations.
 #[proc_macro_attribute]
-pub fn impl_ops_rprim(attr: TokenStream, item: TokenStream) -> TokenStream {
-    let trait_path = parse_macro_input!(attr as syn::TypePath);
-    let fn_item = parse_macro_input!(item as syn::ItemFn);
-    let fn_name = fn_item.sig.ident;
-    let fn_generics = fn_item.sig.generics;
-    let mut fn_args = fn_item.sig.inputs.into_iter();
+pub fn impl_ops(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(attr as OpArgs);
+    let fn_item = parse_macro_input!(item with parse_operator_fn);
 
-    const INSUFFICIENT_ARGS_MSG: &str = "Requires two arguments (self: T1, rhs: T2).";
+    if let Err(e) = check_signature(&args, &fn_item) {
+        return e.to_compile_error().into();
+    }
 
-    let lhs = fn_args.next().expect(INSUFFICIENT_ARGS_MSG);
-    let rhs = fn_args.next().expect(INSUFFICIENT_ARGS_MSG);
-    
-    let lhs = match lhs {
-        syn::FnArg::Receiver(e) => e,
-        _ => { panic!("Error processing first argument.")}
+    TokenStream::from(for_each_trait(args, fn_item, expand_ops))
+}
+
+fn expand_ops(args: OpArgs, mut fn_item: syn::ItemFn) -> proc_macro2::TokenStream {
+    if let Err(e) = check_trait_without_generics(&args.trait_path) {
+        return e.to_compile_error();
+    }
+
+    if let Some(bound) = &args.rhs_fn {
+        if let Err(e) = generic_rhs_fn(&mut fn_item.sig, bound) {
+            return e.to_compile_error();
+        }
+    }
+
+    let (lhs, rhs) = match binary_params(&fn_item.sig) {
+        Ok(params) => params,
+        Err(e) => return e.to_compile_error()
     };
 
-    let rhs = match rhs {
-        syn::FnArg::Typed(e) => e,
-        _ => { panic!("Error processing second argument.")}
+    if let Err(e) = check_borrowed_rhs_unlisted(&args.rhs_types, &rhs.ty) {
+        return e.to_compile_error();
+    }
+
+    let fn_output = match binary_output_type(&fn_item.sig) {
+        Ok(output) => output,
+        Err(e) => return e.to_compile_error()
     };
+    let fn_output = output_of_self(fn_output, &lhs.ty);
+
+    let forwarded = forwarded_options(&args);
+    let trait_path = &args.trait_path;
+    let fn_name = trait_method(trait_path, fn_item.sig.ident);
+    let fn_generics= fn_item.sig.generics;
 
+    // An operand declared as a reference is only ever borrowed, so only the permutations
+    // borrowing it are generated, instead of borrowing the reference again.
     let lhs_pat = &lhs.self_token;
-    let lhs_type = &lhs.ty;
+    let lhs_declared = &lhs.ty;
+    let (lhs_type, lhs_borrowed) = declared_referent(lhs_declared);
     let rhs_pat = &rhs.pat;
-    let rhs_type = &rhs.ty;
-    
-    let fn_body = fn_item.block;    
-    let fn_output = match fn_item.sig.output {
-        syn::ReturnType::Type(_, typ) => typ,
-        _ => { panic!("Function must contain a return type.") }
+    // `Self` would be the borrowed type in the permutations implemented for `&T`.
+    let rhs_declared = replace_self_in_type(&rhs.ty, lhs_type);
+    let (rhs_type, rhs_borrowed) = declared_referent(&rhs_declared);
+    let fn_generics = replace_self_in_generics(fn_generics, lhs_type);
+
+    let fn_body = match &args.push_field {
+        Some(field) if args.deref_operands || args.newtype.is_some() => {
+            return syn::Error::new_spanned(field, "`push_field` can't be used with `deref_operands` or `newtype`.")
+                .to_compile_error();
+        }
+        Some(field) => match push_body(field, &fn_item.block, rhs_pat) {
+            Ok(body) => body,
+            Err(e) => return e.to_compile_error()
+        },
+        None => fn_item.block
     };
 
+    let output_name = args.output_name();
+    let fn_body = match alias_output(args.out_alias.as_ref(), &output_name, fn_body) {
+        Ok(block) => unwrap_or_panic(args.panics_on_err.as_ref(), block),
+        Err(e) => return e.to_compile_error()
+    };
     let attrs = fn_item.attrs;
-    
+
     let (comments, other_tkns) = extract_comments(&attrs);
-    
+
+    let where_clause = &fn_generics.where_clause;
+
+    // The permutations with a borrowed operand only forward to the operator, so they're
+    // worth inlining even when the owned permutation isn't.
+    let ref_tkns = if args.inline_refs {
+        quote! { #[inline] }
+    } else {
+        proc_macro2::TokenStream::new()
+    };
+
+    // With `clone_refs`, the permutations with a borrowed operand clone it and forward to
+    // the owned permutation, so only they require the borrowed operand to be `Clone`.
+    // With `deref_operands`, it's the other way around: every other permutation borrows
+    // its operands and forwards to the fully borrowed permutation, so the body always
+    // sees references.
+    let forward_to = match (args.clone_refs || args.push_field.is_some(), args.deref_operands) {
+        (false, false) => None,
+        (true, false) => Some(false),
+        (false, true) => Some(true),
+        (true, true) => {
+            return syn::Error::new_spanned(trait_path, "`clone_refs` and `deref_operands` can't be used together.")
+                .to_compile_error();
+        }
+    };
+
+    // Those forward to the owned permutation, which isn't generated for a borrowed operand.
+    if forward_to == Some(false) && (lhs_borrowed || rhs_borrowed) {
+        return syn::Error::new_spanned(trait_path, "`clone_refs` and `push_field` require operands that aren't declared as references.")
+            .to_compile_error();
+    }
+
+    // The permutation that the others forward to has to be generated.
+    match forward_to {
+        Some(false) if !args.selects(Permutation::OwnedOwned) => {
+            return syn::Error::new_spanned(trait_path, "`clone_refs` and `push_field` forward to `owned_owned`, which must be generated.")
+                .to_compile_error();
+        }
+        Some(true) if !args.selects(Permutation::RefRef) => {
+            return syn::Error::new_spanned(trait_path, "`deref_operands` forwards to `ref_ref`, which must be generated.")
+                .to_compile_error();
+        }
+        _ => {}
+    }
+
+    // With `newtype`, the permutations with a borrowed operand project the field out of
+    // it and apply the operator to the borrowed field, so they never clone anything.
+    if args.newtype.is_some() && forward_to.is_some() {
+        return syn::Error::new_spanned(trait_path, "`newtype` can't be used with `clone_refs` or `deref_operands`.")
+            .to_compile_error();
+    }
+
+    let rhs_ident = match rhs_pat.as_ref() {
+        syn::Pat::Ident(pat) => Some(&pat.ident),
+        _ if forward_to.is_none() && args.newtype.is_none() => None,
+        _ => {
+            return syn::Error::new_spanned(rhs_pat, "`clone_refs`, `deref_operands` and `newtype` require `rhs` to be an identifier.")
+                .to_compile_error();
+        }
+    };
+
+    // A generic `rhs` that isn't tied to `self` already covers the borrowed types, so the
+    // borrowed `rhs` permutations would overlap with the owned ones.
+    let rhs_free = !rhs_borrowed && is_free_type_param(rhs_type, &fn_generics, lhs_type);
+
+    if args.deref_operands && rhs_free {
+        return syn::Error::new_spanned(rhs_type, "`deref_operands` requires `rhs` to be borrowable.")
+            .to_compile_error();
+    }
+
+    // A boxed operand is only ever taken by value, as a `&Box<T>` would rarely be at hand.
+    let lhs_boxed = !lhs_borrowed && is_boxed(lhs_type);
+    let rhs_boxed = !rhs_borrowed && is_boxed(rhs_type);
+
+    if args.deref_operands && (lhs_boxed || rhs_boxed) {
+        return syn::Error::new_spanned(trait_path, "`deref_operands` requires operands that aren't boxed.")
+            .to_compile_error();
+    }
+
+    // A bare type parameter `rhs` tied to `self`, e.g. `rhs: T` for `self: Wrapper<T>`, is
+    // cloned by the permutations borrowing it, which forward to the one with a borrowed
+    // `self` and an owned `rhs`. The body then always sees a `T`, rather than a `&T` that
+    // its bounds wouldn't cover.
+    let rhs_generic = forward_to.is_none()
+        && args.newtype.is_none()
+        && rhs_ident.is_some()
+        && !rhs_borrowed
+        && !rhs_free
+        && !lhs_boxed
+        && type_param(rhs_type, &fn_generics).is_some()
+        && args.selects(Permutation::RefOwned);
+
+    // With a boxed `self`, the body is emitted once in the permutation borrowing `rhs`,
+    // which the owned one forwards to.
+    let boxed_forward = forward_to.is_none()
+        && args.newtype.is_none()
+        && rhs_ident.is_some()
+        && lhs_boxed
+        && !rhs_boxed
+        && !rhs_borrowed
+        && !rhs_free
+        && args.selects(Permutation::OwnedRef);
+
+    // Otherwise, the body is still only emitted once when the operands can be borrowed, so
+    // that it's only compiled once. As with `deref_operands`, it's emitted in the fully
+    // borrowed permutation, which is the one it must compile for anyway.
+    let forward_to = match forward_to {
+        None if args.newtype.is_none() && rhs_ident.is_some() && !rhs_free && !rhs_generic && !lhs_boxed && !rhs_boxed && args.selects(Permutation::RefRef) => Some(true),
+        forward_to => forward_to
+    };
+
+    let arm = |lhs_ref: bool, rhs_ref: bool| {
+        if let (Some(field), Some(rhs_ident)) = (&args.newtype, rhs_ident) {
+            if !lhs_ref && !rhs_ref {
+                return (fn_body.to_token_stream(), where_clause.clone());
+            }
+
+            let project = |operand: proc_macro2::TokenStream, is_ref: bool| {
+                if is_ref { quote! { &#operand.#field } } else { quote! { #operand.#field } }
+            };
+
+            let lhs_arg = project(quote! { self }, lhs_ref);
+            let rhs_arg = if same_type(rhs_type, lhs_type) {
+                project(quote! { #rhs_ident }, rhs_ref)
+            } else {
+                quote! { #rhs_ident }
+            };
+
+            let value = quote! { #trait_path::#fn_name(#lhs_arg, #rhs_arg) };
+            let body = if same_type(&fn_output, lhs_type) {
+                quote! { { Self::#output_name { #field: #value } } }
+            } else {
+                quote! { { #value } }
+            };
+
+            return (body, where_clause.clone());
+        }
+
+        if let (true, Some(rhs_ident)) = (rhs_generic, rhs_ident) {
+            if lhs_ref && !rhs_ref {
+                return (fn_body.to_token_stream(), where_clause.clone());
+            }
+
+            let mut arm_where = where_clause.clone().unwrap_or_else(|| syn::parse_quote!(where));
+            let lhs_arg = if lhs_ref { quote! { self } } else { quote! { &self } };
+            let rhs_arg = if rhs_ref {
+                arm_where.predicates.push(syn::parse_quote!(#rhs_type: Clone));
+                quote! { #rhs_ident.clone() }
+            } else {
+                quote! { #rhs_ident }
+            };
+
+            let body = quote! {
+                {
+                    <&#lhs_type as #trait_path<#rhs_type>>::#fn_name(#lhs_arg, #rhs_arg)
+                }
+            };
+
+            return (body, Some(arm_where));
+        }
+
+        if let (true, Some(rhs_ident)) = (boxed_forward, rhs_ident) {
+            if rhs_ref {
+                return (fn_body.to_token_stream(), where_clause.clone());
+            }
+
+            let body = quote! {
+                {
+                    <#lhs_type as #trait_path<&#rhs_type>>::#fn_name(self, &#rhs_ident)
+                }
+            };
+
+            return (body, where_clause.clone());
+        }
+
+        let (Some(to_refs), Some(rhs_ident)) = (forward_to, rhs_ident) else {
+            return (fn_body.to_token_stream(), where_clause.clone());
+        };
+
+        if lhs_ref == to_refs && rhs_ref == to_refs {
+            return (fn_body.to_token_stream(), where_clause.clone());
+        }
+
+        let mut arm_where = where_clause.clone().unwrap_or_else(|| syn::parse_quote!(where));
+        let (lhs_arg, rhs_arg) = if to_refs {
+            (
+                if lhs_ref { quote! { self } } else { quote! { &self } },
+                if rhs_ref { quote! { #rhs_ident } } else { quote! { &#rhs_ident } }
+            )
+        } else {
+            // A `Clone` bound on a concrete type that isn't `Clone` is an error rather than
+            // leaving the permutation out, so it's only added for generic operands. A concrete
+            // operand is cloned through its type instead, so that a missing `Clone` is
+            // reported on it.
+            let mut clone = |ty: &syn::Type, operand: proc_macro2::TokenStream| {
+                if fn_generics.type_params().any(|param| mentions_ident(ty.to_token_stream(), &param.ident)) {
+                    arm_where.predicates.push(syn::parse_quote!(#ty: Clone));
+                    quote! { #operand.clone() }
+                } else {
+                    let span = syn::spanned::Spanned::span(ty);
+                    quote::quote_spanned! { span => <#ty as ::core::clone::Clone>::clone(#operand) }
+                }
+            };
+
+            (
+                if lhs_ref { clone(lhs_type, quote! { self }) } else { quote! { self } },
+                if rhs_ref { clone(rhs_type, quote! { #rhs_ident }) } else { quote! { #rhs_ident } }
+            )
+        };
+
+        let body = if to_refs {
+            quote! {
+                {
+                    <&#lhs_type as #trait_path<&#rhs_type>>::#fn_name(#lhs_arg, #rhs_arg)
+                }
+            }
+        } else {
+            quote! {
+                {
+                    <#lhs_type as #trait_path<#rhs_type>>::#fn_name(#lhs_arg, #rhs_arg)
+                }
+            }
+        };
+
+        (body, Some(arm_where))
+    };
+
+    // With `doc_all`, the documentation is repeated on every permutation, but its examples
+    // only run for the owned permutation.
+    let ref_comments = if args.doc_all {
+        ignore_doc_examples(&attrs)
+    } else {
+        proc_macro2::TokenStream::new()
+    };
+
+    // The permutations, in the order they're generated, as whether `self` and `rhs` are
+    // borrowed. Operands declared as references are always borrowed.
+    let permutations = [(false, false), (true, true), (false, true), (true, false)]
+        .into_iter()
+        .filter(|&(lhs_ref, rhs_ref)| (lhs_ref || !lhs_borrowed) && (rhs_ref || !rhs_borrowed) && !(rhs_ref && rhs_free))
+        .filter(|&(lhs_ref, rhs_ref)| (!lhs_ref || !lhs_boxed) && (!rhs_ref || !rhs_boxed))
+        .filter(|&(lhs_ref, rhs_ref)| args.selects(Permutation::of(lhs_ref, rhs_ref)))
+        .collect::<Vec<_>>();
+
+    if permutations.is_empty() {
+        return syn::Error::new_spanned(trait_path, "None of the permutations are left to generate.")
+            .to_compile_error();
+    }
+
+    let mut token = proc_macro2::TokenStream::new();
+
+    // The documentation goes on the first permutation, which is the owned one unless it's
+    // not generated.
+    for (i, (lhs_ref, rhs_ref)) in permutations.into_iter().enumerate() {
+        let (body, arm_where) = arm(lhs_ref, rhs_ref);
+
+        let docs = if i == 0 { &comments } else { &ref_comments };
+        let inline = if lhs_ref || rhs_ref { &ref_tkns } else { &proc_macro2::TokenStream::new() };
+
+        // An operand declared as a reference keeps its declared type, along with its lifetime
+        // that the output may be tied to.
+        let lhs_arg = match (lhs_ref, rhs_ref) {
+            (false, false) => quote! { #lhs },
+            (false, true) => quote! { #lhs_pat: #lhs_type },
+            (true, _) if lhs_borrowed => quote! { #lhs_pat: #lhs_declared },
+            (true, _) => quote! { #lhs_pat: &#lhs_type },
+        };
+        let rhs_arg = match rhs_ref {
+            true if rhs_borrowed => quote! { #rhs_pat: #rhs_declared },
+            true => quote! { #rhs_pat: &#rhs_type },
+            false => quote! { #rhs_pat: #rhs_type }
+        };
+
+        token.extend(quote!{
+            #docs
+            #other_tkns
+            #inline
+            #[opimps::impl_op(#trait_path #forwarded)]
+            fn #fn_name #fn_generics (#lhs_arg, #rhs_arg) -> #fn_output #arm_where
+                #body
+        });
+    }
+
+    // With `with_mut_lhs`, the operator is also implemented for a mutably borrowed `self`,
+    // whose permutations reborrow it and forward to those borrowing it.
+    if args.with_mut_lhs {
+        let Some(rhs_ident) = rhs_ident else {
+            return syn::Error::new_spanned(rhs_pat, "`with_mut_lhs` requires `rhs` to be an identifier.")
+                .to_compile_error();
+        };
+
+        let rhs_forms = [false, true]
+            .into_iter()
+            .filter(|&rhs_ref| (rhs_ref || !rhs_borrowed) && !(rhs_ref && (rhs_free || rhs_boxed)))
+            .collect::<Vec<_>>();
+
+        if lhs_boxed || rhs_forms.iter().any(|&rhs_ref| !args.selects(Permutation::of(true, rhs_ref))) {
+            return syn::Error::new_spanned(trait_path, "`with_mut_lhs` forwards to the permutations borrowing `self`, which must be generated.")
+                .to_compile_error();
+        }
+
+        for rhs_ref in rhs_forms {
+            let (_, arm_where) = arm(true, rhs_ref);
+
+            let rhs_declared = match rhs_ref {
+                true if rhs_borrowed => quote! { #rhs_declared },
+                true => quote! { &#rhs_type },
+                false => quote! { #rhs_type }
+            };
+
+            token.extend(quote! {
+                #other_tkns
+                #ref_tkns
+                #[opimps::impl_op(#trait_path #forwarded)]
+                fn #fn_name #fn_generics (#lhs_pat: &mut #lhs_type, #rhs_ident: #rhs_declared) -> #fn_output #arm_where {
+                    <&#lhs_type as #trait_path<#rhs_declared>>::#fn_name(&*#lhs_pat, #rhs_ident)
+                }
+            });
+        }
+    }
+
+    if args.gen_test {
+        if !fn_generics.params.is_empty() {
+            return syn::Error::new_spanned(&fn_generics, "`gen_test` doesn't support generic operators.")
+                .to_compile_error();
+        }
+
+        if lhs_borrowed || rhs_borrowed || lhs_boxed || rhs_boxed || args.only.is_some() || !args.skip.is_empty() {
+            return syn::Error::new_spanned(trait_path, "`gen_test` requires every permutation, with operands that aren't declared as references or boxed.")
+                .to_compile_error();
+        }
+
+        token.extend(permutation_test(trait_path, &fn_name, lhs_type, rhs_type, args.sample.as_ref(), args.rhs_sample.as_ref()));
+    }
+
+    // With `commutative`, the operator is also implemented for the swapped operands, which
+    // forward to the declared ones.
+    if args.commutative {
+        if same_type(lhs_type, rhs_type) {
+            return syn::Error::new_spanned(trait_path, "`commutative` requires the operands to have different types.")
+                .to_compile_error();
+        }
+
+        // The swapped implementations would be generic over the type of `self`, covering the
+        // declared ones as well.
+        if rhs_free {
+            return syn::Error::new_spanned(rhs_type, "`commutative` requires `rhs` not to be a type parameter, as the swapped implementations would overlap with the declared ones.")
+                .to_compile_error();
+        }
+
+        // The swapped implementations mirror the selected permutations, so that those left out
+        // to avoid a conflict are left out for the swapped operands as well.
+        let mut selection = Vec::new();
+
+        if let Some(only) = &args.only {
+            let names = only.iter().map(|permutation| permutation.swapped().ident());
+            selection.push(quote! { only(#(#names),*) });
+        }
+
+        if !args.skip.is_empty() {
+            let names = args.skip.iter().map(|permutation| permutation.swapped().ident());
+            selection.push(quote! { skip(#(#names),*) });
+        }
+
+        let swapped_options = match (&forwarded, selection.is_empty()) {
+            (_, true) => forwarded.clone(),
+            (Some(forwarded), false) => Some(quote! { #forwarded, #(#selection),* }),
+            (None, false) => Some(quote! { ; #(#selection),* })
+        };
+
+        token.extend(quote! {
+            #[opimps::impl_ops(#trait_path #swapped_options)]
+            fn #fn_name #fn_generics (self: #rhs_declared, rhs: #lhs_declared) -> #fn_output #where_clause {
+                #trait_path::#fn_name(rhs, self)
+            }
+        });
+    }
+
+    token
+}
+
+/// Implements `Shl` and `Shr` for the permutations of owned and borrowed data, as
+/// `impl_ops(Shl)` and `impl_ops(Shr)` would, from the functions `shl` and `shr` of an
+/// inline module. The functions are emitted in place of the module, along with its
+/// attributes, and the options of the attribute apply to both.
+///
+/// ```
+/// #[derive(Debug, PartialEq)]
+/// pub struct Bits(u8);
+///
+/// #[opimps::impl_shl_shr_ops]
+/// mod shifts {
+///     fn shl(self: Bits, rhs: u32) -> Bits {
+///         Bits(self.0 << rhs)
+///     }
+///
+///     fn shr(self: Bits, rhs: u32) -> Bits {
+///         Bits(self.0 >> rhs)
+///     }
+/// }
+///
+/// let bits = Bits(0b0110);
+///
+/// assert_eq!(Bits(0b1100), &bits << 1);
+/// assert_eq!(Bits(0b0011), bits >> &1);
+/// ```
+#[proc_macro_attribute]
+pub fn impl_shl_shr_ops(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let item_mod = parse_macro_input!(item as syn::ItemMod);
+
+    TokenStream::from(expand_shl_shr_ops(attr.into(), item_mod))
+}
+
+fn expand_shl_shr_ops(attr: proc_macro2::TokenStream, item_mod: syn::ItemMod) -> proc_macro2::TokenStream {
+    let Some((_, items)) = &item_mod.content else {
+        return syn::Error::new_spanned(&item_mod, "Requires an inline module with the functions `shl` and `shr`.")
+            .to_compile_error();
+    };
+
+    let mut shl = None;
+    let mut shr = None;
+
+    for item in items {
+        match item {
+            syn::Item::Fn(fn_item) if fn_item.sig.ident == "shl" && shl.is_none() => shl = Some(fn_item),
+            syn::Item::Fn(fn_item) if fn_item.sig.ident == "shr" && shr.is_none() => shr = Some(fn_item),
+            item => {
+                return syn::Error::new_spanned(item, "Only the functions `shl` and `shr` can be in the module, once each.")
+                    .to_compile_error();
+            }
+        }
+    }
+
+    let (Some(shl), Some(shr)) = (shl, shr) else {
+        return syn::Error::new_spanned(&item_mod.ident, "Requires both of the functions `shl` and `shr`.")
+            .to_compile_error();
+    };
+
+    let attrs = &item_mod.attrs;
+    let options = (!attr.is_empty()).then(|| quote! { ; #attr });
+
+    quote! {
+        #(#attrs)*
+        #[opimps::impl_ops(::core::ops::Shl #options)]
+        #shl
+
+        #(#attrs)*
+        #[opimps::impl_ops(::core::ops::Shr #options)]
+        #shr
+    }
+}
+
+/// Implements the permutations of owned and borrowed data, with `rhs` being a 
+/// primitive value and `self` being a structure.
+/// 
+/// ```
+/// use std::ops::Mul;
+///
+/// pub struct ANumber {
+///     val: i32
+/// }
+///
+/// #[opimps::impl_ops_rprim(Mul)] 
+/// fn mul(self: ANumber, rhs: i32) -> i32 {
+///     return self.val * rhs;
+/// }
+/// 
+/// let a = ANumber { val: 4 };
+/// let b = 7;
+/// 
+/// assert_eq!(28, &a * b);
+/// assert_eq!(28, a * b);
+/// ```
+#[proc_macro_attribute]
+pub fn impl_ops_rprim(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(attr as OpArgs);
+    let fn_item = parse_macro_input!(item with parse_operator_fn);
+
+    if let Err(e) = check_signature(&args, &fn_item) {
+        return e.to_compile_error().into();
+    }
+
+    TokenStream::from(for_each_trait(args, fn_item, expand_ops_rprim))
+}
+
+fn expand_ops_rprim(args: OpArgs, fn_item: syn::ItemFn) -> proc_macro2::TokenStream {
+    if let Err(e) = check_trait_without_generics(&args.trait_path) {
+        return e.to_compile_error();
+    }
+
+    let (lhs, rhs) = match binary_params(&fn_item.sig) {
+        Ok(params) => params,
+        Err(e) => return e.to_compile_error()
+    };
+
+    let fn_output = match binary_output_type(&fn_item.sig) {
+        Ok(output) => output,
+        Err(e) => return e.to_compile_error()
+    };
+    let fn_output = output_of_self(fn_output, &lhs.ty);
+
+    let forwarded = forwarded_options(&args);
+    let output = args.output_name();
+    let trait_path = args.trait_path;
+    let fn_name = trait_method(&trait_path, fn_item.sig.ident);
+    let fn_generics = fn_item.sig.generics;
+
+    let lhs_pat = &lhs.self_token;
+    let lhs_type = &lhs.ty;
+    let rhs_pat = &rhs.pat;
+    let rhs_type = &rhs.ty;
+    
+    let fn_body = match alias_output(args.out_alias.as_ref(), &output, fn_item.block) {
+        Ok(block) => unwrap_or_panic(args.panics_on_err.as_ref(), block),
+        Err(e) => return e.to_compile_error()
+    };
+    let attrs = fn_item.attrs;
+    
+    let (comments, other_tkns) = extract_comments(&attrs);
+    
     let where_clause = &fn_generics.where_clause;
 
+    // A `self` declared as a reference is only ever borrowed, so it's implemented as is.
+    if declared_referent(lhs_type).1 {
+        let token = quote!{
+            #comments
+            #other_tkns
+            #[opimps::impl_op(#trait_path #forwarded)]
+            fn #fn_name #fn_generics (#lhs, #rhs) -> #fn_output #where_clause
+                #fn_body
+        };
+
+        return token;
+    }
+
+    // The body is only emitted once, in the borrowed permutation, which the owned one
+    // forwards to.
+    let owned_body = match rhs_pat.as_ref() {
+        syn::Pat::Ident(pat) => {
+            let rhs_ident = &pat.ident;
+            quote! { { <&#lhs_type as #trait_path<#rhs_type>>::#fn_name(&self, #rhs_ident) } }
+        }
+        _ => fn_body.to_token_stream()
+    };
+
     let token = quote!{
         #comments
         #other_tkns
-        #[opimps::impl_op(#trait_path)]
+        #[opimps::impl_op(#trait_path #forwarded)]
         fn #fn_name #fn_generics (#lhs, #rhs) -> #fn_output #where_clause
-            #fn_body
+            #owned_body
 
         #other_tkns
-        #[opimps::impl_op(#trait_path)]
+        #[opimps::impl_op(#trait_path #forwarded)]
         fn #fn_name #fn_generics (#lhs_pat: &#lhs_type, #rhs_pat: #rhs_type) -> #fn_output #where_clause
             #fn_body
     };
     
-    TokenStream::from(token)
+    token
 }
 
 /// Implements the permutations of owned and borrowed data, with `self` being a 
@@ -421,54 +1243,176 @@ pub fn impl_ops_rprim(attr: TokenStream, item: TokenStream) -> TokenStream {
 /// ```
 #[proc_macro_attribute]
 pub fn impl_ops_lprim(attr: TokenStream, item: TokenStream) -> TokenStream {
-    let trait_path = parse_macro_input!(attr as syn::TypePath);
-    let fn_item = parse_macro_input!(item as syn::ItemFn);
-    let fn_name = fn_item.sig.ident;
-    let fn_generics = fn_item.sig.generics;
-    let mut fn_args = fn_item.sig.inputs.into_iter();
+    let args = parse_macro_input!(attr as OpArgs);
+    let fn_item = parse_macro_input!(item with parse_operator_fn);
 
-    const INSUFFICIENT_ARGS_MSG: &str = "Requires two arguments (self: T1, rhs: T2).";
+    if let Err(e) = check_signature(&args, &fn_item) {
+        return e.to_compile_error().into();
+    }
 
-    let lhs = fn_args.next().expect(INSUFFICIENT_ARGS_MSG);
-    let rhs = fn_args.next().expect(INSUFFICIENT_ARGS_MSG);
-    
-    let lhs = match lhs {
-        syn::FnArg::Receiver(e) => e,
-        _ => { panic!("Error processing first argument.")}
+    TokenStream::from(for_each_trait(args, fn_item, expand_ops_lprim))
+}
+
+fn expand_ops_lprim(args: OpArgs, fn_item: syn::ItemFn) -> proc_macro2::TokenStream {
+    if let Err(e) = check_trait_without_generics(&args.trait_path) {
+        return e.to_compile_error();
+    }
+
+    let (lhs, rhs) = match binary_params(&fn_item.sig) {
+        Ok(params) => params,
+        Err(e) => return e.to_compile_error()
     };
 
-    let rhs = match rhs {
-        syn::FnArg::Typed(e) => e,
-        _ => { panic!("Error processing second argument.")}
+    if let Err(e) = check_borrowed_rhs_unlisted(&args.rhs_types, &rhs.ty) {
+        return e.to_compile_error();
+    }
+
+    let fn_output = match binary_output_type(&fn_item.sig) {
+        Ok(output) => output,
+        Err(e) => return e.to_compile_error()
     };
+    let fn_output = output_of_self(fn_output, &lhs.ty);
+
+    let forwarded = forwarded_options(&args);
+    let output = args.output_name();
+    let trait_path = args.trait_path;
+    let fn_name = trait_method(&trait_path, fn_item.sig.ident);
+    let fn_generics = fn_item.sig.generics;
 
     let rhs_pat = &rhs.pat;
     let rhs_type = &rhs.ty;
     
-    let fn_body = fn_item.block;    
-    let fn_output = match fn_item.sig.output {
-        syn::ReturnType::Type(_, typ) => typ,
-        _ => { panic!("Function must contain a return type.") }
+    let fn_body = match alias_output(args.out_alias.as_ref(), &output, fn_item.block) {
+        Ok(block) => unwrap_or_panic(args.panics_on_err.as_ref(), block),
+        Err(e) => return e.to_compile_error()
     };
-
     let attrs = fn_item.attrs;
 
     let (comments, other_tkns) = extract_comments(&attrs);
     
     let where_clause = &fn_generics.where_clause;
+
+    // An `rhs` declared as a reference is only ever borrowed, so it's implemented as is.
+    if declared_referent(rhs_type).1 {
+        let token = quote!{
+            #comments
+            #other_tkns
+            #[opimps::impl_op(#trait_path #forwarded)]
+            fn #fn_name #fn_generics (#lhs, #rhs) -> #fn_output #where_clause
+                #fn_body
+        };
+
+        return token;
+    }
+
+    // The body is only emitted once, in the borrowed permutation, which the owned one
+    // forwards to.
+    let owned_body = match rhs_pat.as_ref() {
+        syn::Pat::Ident(pat) => {
+            let lhs_type = &lhs.ty;
+            let rhs_ident = &pat.ident;
+            quote! { { <#lhs_type as #trait_path<&#rhs_type>>::#fn_name(self, &#rhs_ident) } }
+        }
+        _ => fn_body.to_token_stream()
+    };
+
     let token = quote!{
         #comments
         #other_tkns
-        #[opimps::impl_op(#trait_path)]
+        #[opimps::impl_op(#trait_path #forwarded)]
         fn #fn_name #fn_generics (#lhs, #rhs) -> #fn_output #where_clause
-            #fn_body
+            #owned_body
         
         #other_tkns
-        #[opimps::impl_op(#trait_path)]
+        #[opimps::impl_op(#trait_path #forwarded)]
         fn #fn_name #fn_generics (#lhs, #rhs_pat: &#rhs_type) -> #fn_output #where_clause
             #fn_body
     };
     
+    token
+}
+
+/// Implements a commutative operator between a primitive value and a structure in both
+/// directions, for the owned and borrowed structure.
+///
+/// The function is implemented as with `impl_ops_lprim` or `impl_ops_rprim`, depending on
+/// which operand is the primitive. The reversed direction swaps the operands and forwards
+/// to it, so the primitive is never borrowed.
+///
+/// ```
+/// use std::ops::Mul;
+///
+/// #[derive(Debug, PartialEq)]
+/// pub struct Meters(f64);
+///
+/// #[opimps::impl_ops_prim(Mul)]
+/// fn mul(self: f64, rhs: Meters) -> Meters {
+///     Meters(self * rhs.0)
+/// }
+///
+/// let m = Meters(1.5);
+///
+/// assert_eq!(Meters(3.0), 2.0 * &m);
+/// assert_eq!(Meters(3.0), &m * 2.0);
+/// assert_eq!(Meters(3.0), m * 2.0);
+/// ```
+#[doc(alias = "impl_ops_commutative_prim")]
+#[proc_macro_attribute]
+pub fn impl_ops_prim(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let attr_tokens = proc_macro2::TokenStream::from(attr.clone());
+    let args = parse_macro_input!(attr as OpArgs);
+    let fn_item = parse_macro_input!(item with parse_operator_fn);
+
+    if let Err(e) = check_signature(&args, &fn_item) {
+        return e.to_compile_error().into();
+    }
+
+    if let Err(e) = check_single_trait(&args) {
+        return e.to_compile_error().into();
+    }
+
+    let forwarded = forwarded_options(&args);
+    let trait_path = &args.trait_path;
+    let sig = &fn_item.sig;
+    let mut fn_args = sig.inputs.iter();
+
+    let (Some(syn::FnArg::Receiver(lhs)), Some(syn::FnArg::Typed(rhs)), None) = (fn_args.next(), fn_args.next(), fn_args.next()) else {
+        return syn::Error::new_spanned(&sig.inputs, "Requires two arguments (self: T1, rhs: T2).")
+            .to_compile_error()
+            .into();
+    };
+
+    let fn_output = match binary_output_type(sig) {
+        Ok(output) => output,
+        Err(e) => return e.to_compile_error().into()
+    };
+    let fn_output = output_of_self(fn_output, &lhs.ty);
+
+    let (declared, reversed) = match (is_primitive(&lhs.ty), is_primitive(&rhs.ty)) {
+        (true, false) => (quote! { impl_ops_lprim }, quote! { impl_ops_rprim }),
+        (false, true) => (quote! { impl_ops_rprim }, quote! { impl_ops_lprim }),
+        _ => {
+            return syn::Error::new_spanned(&sig.inputs, "Exactly one of the operands must be a primitive type.")
+                .to_compile_error()
+                .into();
+        }
+    };
+
+    let fn_name = trait_method(trait_path, sig.ident.clone());
+    let fn_generics = &sig.generics;
+    let where_clause = &fn_generics.where_clause;
+    let (lhs_type, rhs_type) = (&lhs.ty, &rhs.ty);
+
+    let token = quote! {
+        #[opimps::#declared(#attr_tokens)]
+        #fn_item
+
+        #[opimps::#reversed(#trait_path #forwarded)]
+        fn #fn_name #fn_generics (self: #rhs_type, rhs: #lhs_type) -> #fn_output #where_clause {
+            #trait_path::#fn_name(rhs, self)
+        }
+    };
+
     TokenStream::from(token)
 }
 
@@ -502,55 +1446,80 @@ pub fn impl_ops_lprim(attr: TokenStream, item: TokenStream) -> TokenStream {
 /// assert_eq!(28, a.val);
 /// assert_eq!(7, b.val);
 /// ```
+///
+/// The trait method returns `()`, so the function can't have a return type.
+///
+/// ```compile_fail
+/// pub struct TestObj {
+///     pub val: i32
+/// }
+///
+/// // Error: Assignment operators can't have a return type.
+/// #[opimps::impl_op_assign(std::ops::MulAssign)]
+/// fn mul_assign(self: TestObj, rhs: TestObj) -> i32 {
+///    self.val *= rhs.val;
+///    self.val
+/// }
+/// ```
 #[proc_macro_attribute]
 pub fn impl_op_assign(attr: TokenStream, item: TokenStream) -> TokenStream {
-    let trait_path = parse_macro_input!(attr as syn::TypePath);
-    let fn_item = parse_macro_input!(item as syn::ItemFn);
-    let fn_name = fn_item.sig.ident;
-    let fn_generics = fn_item.sig.generics;
-    let mut fn_args = fn_item.sig.inputs.into_iter();
+    let args = parse_macro_input!(attr as OpArgs);
+    let fn_item = parse_macro_input!(item with parse_operator_fn);
 
-    const INSUFFICIENT_ARGS_MSG: &str = "Requires two arguments (self: T1, rhs: T2).";
-
-    let lhs = fn_args.next().expect(INSUFFICIENT_ARGS_MSG);
-    let rhs = fn_args.next().expect(INSUFFICIENT_ARGS_MSG);
+    if let Err(e) = check_signature(&args, &fn_item) {
+        return e.to_compile_error().into();
+    }
 
-    let attrs = fn_item.attrs;
+    TokenStream::from(for_each_trait(args, fn_item, expand_op_assign))
+}
 
-    let lhs = match lhs {
-        syn::FnArg::Receiver(e) => e,
-        _ => { panic!("Error processing first argument.")}
+fn expand_op_assign(args: OpArgs, fn_item: syn::ItemFn) -> proc_macro2::TokenStream {
+    let (lhs, rhs) = match binary_params(&fn_item.sig) {
+        Ok(params) => params,
+        Err(e) => return e.to_compile_error()
     };
 
-    let rhs = match rhs {
-        syn::FnArg::Typed(e) => e,
-        _ => { panic!("Error processing second argument.")}
-    };
+    if let Err(e) = check_assign_output(&fn_item.sig) {
+        return e.to_compile_error();
+    }
 
-    let mut other_tkns = proc_macro2::TokenStream::new();
+    if args.prim && !is_primitive(&rhs.ty) {
+        return syn::Error::new_spanned(&rhs.ty, "`prim` requires a primitive right-hand side, e.g. `i32`.").to_compile_error();
+    }
 
-    attrs.into_iter().fold(
-        &mut other_tkns,
-        |tkn, attr|{ tkn.extend(attr.to_token_stream()); tkn }
-    );
+    let trait_path = args.trait_path;
+    let fn_name = trait_method(&trait_path, fn_item.sig.ident);
+    let fn_generics = fn_item.sig.generics;
+
+    let attrs = fn_item.attrs;
+
+    // The `cfg` attributes gate the implementation as a whole, while the others go on the method.
+    let (cfg_tkns, other_tkns) = extract_cfgs(&attrs);
 
     let lhs_type = &lhs.ty;
     let rhs_type = &rhs.ty;
 
     let fn_body = fn_item.block;
 
-    let where_clause = &fn_generics.where_clause;
+    let (impl_generics, _, where_clause) = fn_generics.split_for_impl();
+
+    let trait_ref = if has_generic_args(&trait_path) {
+        quote! { #trait_path }
+    } else {
+        quote! { #trait_path<#rhs_type> }
+    };
     
     let token = quote! {
-        impl #fn_generics #trait_path<#rhs_type> for #lhs_type #where_clause {
+        #cfg_tkns
+        #[automatically_derived]
+        impl #impl_generics #trait_ref for #lhs_type #where_clause {
             #other_tkns
-            fn #fn_name (&mut self, #rhs) {
+            fn #fn_name (&mut self, #rhs)
                 #fn_body
-            }
         }
     };
 
-    TokenStream::from(token)
+    token
 }
 
 /// Implement assignment-based operators for both borrowed and owned objects on the right-hand side.
@@ -578,34 +1547,67 @@ pub fn impl_op_assign(attr: TokenStream, item: TokenStream) -> TokenStream {
 /// assert_eq!(11, a.val);
 /// assert_eq!(7, b.val);
 /// ```
+///
+/// The borrowed right-hand side is implemented whatever its type by default, as the standard
+/// library does with `AddAssign<&i32>` for `i32`. With the `prim` option, only the owned
+/// right-hand side is implemented for a primitive.
+///
+/// ```
+/// pub struct Cell(i32);
+///
+/// #[opimps::impl_ops_assign(std::ops::AddAssign; prim)]
+/// fn add_assign(self: Cell, rhs: i32) {
+///     self.0 += rhs;
+/// }
+///
+/// let mut cell = Cell(4);
+/// cell += 7;
+///
+/// assert_eq!(11, cell.0);
+/// ```
 #[proc_macro_attribute]
 pub fn impl_ops_assign(attr: TokenStream, item: TokenStream) -> TokenStream {
-    let trait_path = parse_macro_input!(attr as syn::TypePath);
-    let fn_item = parse_macro_input!(item as syn::ItemFn);
-    let fn_name = fn_item.sig.ident;
-    let fn_generics = fn_item.sig.generics;
-    let mut fn_args = fn_item.sig.inputs.into_iter();
+    let args = parse_macro_input!(attr as OpArgs);
+    let fn_item = parse_macro_input!(item with parse_operator_fn);
 
-    const INSUFFICIENT_ARGS_MSG: &str = "Requires two arguments (self: T1, rhs: T2).";
+    if let Err(e) = check_signature(&args, &fn_item) {
+        return e.to_compile_error().into();
+    }
 
-    let lhs = fn_args.next().expect(INSUFFICIENT_ARGS_MSG);
-    let rhs = fn_args.next().expect(INSUFFICIENT_ARGS_MSG);
+    TokenStream::from(for_each_trait(args, fn_item, expand_ops_assign))
+}
 
-    let attrs = fn_item.attrs;
+fn expand_ops_assign(args: OpArgs, fn_item: syn::ItemFn) -> proc_macro2::TokenStream {
+    if let Err(e) = check_trait_without_generics(&args.trait_path) {
+        return e.to_compile_error();
+    }
 
-    let lhs = match lhs {
-        syn::FnArg::Receiver(e) => e,
-        _ => { panic!("Error processing first argument.")}
+    let (lhs, rhs) = match binary_params(&fn_item.sig) {
+        Ok(params) => params,
+        Err(e) => return e.to_compile_error()
     };
 
-    let rhs = match rhs {
-        syn::FnArg::Typed(e) => e,
-        _ => { panic!("Error processing second argument.")}
-    };
+    if let Err(e) = check_borrowed_rhs_unlisted(&args.rhs_types, &rhs.ty) {
+        return e.to_compile_error();
+    }
+
+    if let Err(e) = check_assign_output(&fn_item.sig) {
+        return e.to_compile_error();
+    }
+
+    if args.prim && !is_primitive(&rhs.ty) {
+        return syn::Error::new_spanned(&rhs.ty, "`prim` requires a primitive right-hand side, e.g. `i32`.").to_compile_error();
+    }
+
+    let trait_path = args.trait_path;
+    let fn_name = trait_method(&trait_path, fn_item.sig.ident);
+    let fn_generics = fn_item.sig.generics;
+
+    let attrs = fn_item.attrs;
 
     let mut other_tkns = proc_macro2::TokenStream::new();
 
-    (&attrs).into_iter().fold(
+    attrs.iter().fold(
         &mut other_tkns,
         |tkn, attr|{ tkn.extend(attr.to_token_stream()); tkn }
     );
@@ -619,13 +1621,35 @@ pub fn impl_ops_assign(attr: TokenStream, item: TokenStream) -> TokenStream {
     let where_clause = &fn_generics.where_clause;
     
     let (comments, other_tkns) = extract_comments(&attrs);
+
+    // A primitive is `Copy`, so its borrowed right-hand side would only be an extra `*rhs`.
+    if args.prim {
+        return quote! {
+            #comments
+            #other_tkns
+            #[opimps::impl_op_assign(#trait_path)]
+            fn #fn_name #fn_generics (#lhs, #rhs) #where_clause
+                #fn_body
+        };
+    }
     
+    // The body is only emitted once, for the borrowed right-hand side, which the owned one
+    // forwards to.
+    let owned_body = match rhs_pat.as_ref() {
+        syn::Pat::Ident(pat) => {
+            let lhs_type = &lhs.ty;
+            let rhs_ident = &pat.ident;
+            quote! { { <#lhs_type as #trait_path<&#rhs_type>>::#fn_name(self, &#rhs_ident) } }
+        }
+        _ => fn_body.to_token_stream()
+    };
+
     let token = quote! {
         #comments
         #other_tkns
         #[opimps::impl_op_assign(#trait_path)]
         fn #fn_name #fn_generics (#lhs, #rhs) #where_clause
-            #fn_body
+            #owned_body
 
         #other_tkns
         #[opimps::impl_op_assign(#trait_path)]
@@ -633,20 +1657,2176 @@ pub fn impl_ops_assign(attr: TokenStream, item: TokenStream) -> TokenStream {
             #fn_body
     };
 
+    token
+}
+
+/// Implements an assignment-based operator and derives the binary operator from it, for both
+/// borrowed and owned objects.
+///
+/// The function is the assignment, implemented as with `impl_ops_assign`. The binary
+/// operator given to the attribute applies the assignment to the owned `self` and returns
+/// it. The borrowed permutations clone their operands, requiring them to be `Clone`.
+///
+/// ```
+/// #[derive(Clone)]
+/// pub struct TestObj {
+///     pub val: i32
+/// }
+///
+/// #[opimps::impl_ops_from_assign(std::ops::Add)]
+/// fn add_assign(self: TestObj, rhs: TestObj) {
+///     self.val += rhs.val;
+/// }
+///
+/// let mut a = TestObj { val: 4 };
+/// let b = TestObj { val: 7 };
+///
+/// assert_eq!(11, (&a + &b).val);
+///
+/// a += &b;
+/// assert_eq!(18, (a + b).val);
+/// ```
+#[proc_macro_attribute]
+pub fn impl_ops_from_assign(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(attr as OpArgs);
+    let fn_item = parse_macro_input!(item with parse_operator_fn);
+
+    if let Err(e) = check_signature(&args, &fn_item) {
+        return e.to_compile_error().into();
+    }
+
+    if let Err(e) = check_single_trait(&args) {
+        return e.to_compile_error().into();
+    }
+
+    let trait_path = &args.trait_path;
+
+    let assign_path = assign_trait(trait_path);
+
+    let (Some(method), Some(assign_method)) = (ops::method_name(trait_path), ops::method_name(&assign_path)) else {
+        return syn::Error::new_spanned(trait_path, "Unknown operator trait; expected a binary operator from `std::ops`.")
+            .to_compile_error()
+            .into();
+    };
+
+    let sig = &fn_item.sig;
+    let mut fn_args = sig.inputs.iter();
+
+    let (Some(syn::FnArg::Receiver(lhs)), Some(syn::FnArg::Typed(rhs)), None) = (fn_args.next(), fn_args.next(), fn_args.next()) else {
+        return syn::Error::new_spanned(&sig.inputs, "Requires two arguments (self: T1, rhs: T2).")
+            .to_compile_error()
+            .into();
+    };
+
+    let fn_generics = &sig.generics;
+    let where_clause = &fn_generics.where_clause;
+    let (lhs_type, rhs_type) = (&lhs.ty, &rhs.ty);
+    let lhs = syn::Ident::new("lhs", proc_macro2::Span::mixed_site());
+
+    let token = quote! {
+        #[opimps::impl_ops_assign(#assign_path)]
+        #fn_item
+
+        #[opimps::impl_ops(#trait_path; clone_refs)]
+        fn #method #fn_generics (self: #lhs_type, rhs: #rhs_type) -> #lhs_type #where_clause {
+            let mut #lhs = self;
+            #assign_path::#assign_method(&mut #lhs, rhs);
+            #lhs
+        }
+    };
+
     TokenStream::from(token)
 }
 
-fn extract_comments(attrs: &Vec<Attribute>) -> (proc_macro2::TokenStream, proc_macro2::TokenStream) {
-    let mut other_tkns = proc_macro2::TokenStream::new();
-    let mut comments = proc_macro2::TokenStream::new();
+/// Implements the permutations of owned and borrowed data and derives the assignment-based
+/// operator from them, for both borrowed and owned objects on the right-hand side.
+///
+/// The function is the binary operator, implemented as with `impl_ops`, and must return the
+/// type of `self`. The assignment replaces `self` with the result of its borrowed
+/// permutations, so the body never needs to move out of `self`.
+///
+/// ```
+/// pub struct TestObj {
+///     pub val: i32
+/// }
+///
+/// #[opimps::impl_assign_from_ops(std::ops::Add)]
+/// fn add(self: TestObj, rhs: TestObj) -> TestObj {
+///     TestObj { val: self.val + rhs.val }
+/// }
+///
+/// let mut a = TestObj { val: 4 };
+/// let b = TestObj { val: 7 };
+///
+/// a += &b;
+/// assert_eq!(11, a.val);
+///
+/// a += b;
+/// assert_eq!(18, a.val);
+/// ```
+#[proc_macro_attribute]
+pub fn impl_assign_from_ops(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let attr_tokens = proc_macro2::TokenStream::from(attr.clone());
+    let args = parse_macro_input!(attr as OpArgs);
+    let fn_item = parse_macro_input!(item with parse_operator_fn);
 
-    for attr in attrs.into_iter() {
-        if attr.path().is_ident("doc") {
-            comments.extend(attr.to_token_stream());
-        } else {
-            other_tkns.extend(attr.to_token_stream());
+    if let Err(e) = check_signature(&args, &fn_item) {
+        return e.to_compile_error().into();
+    }
+
+    if let Err(e) = check_single_trait(&args) {
+        return e.to_compile_error().into();
+    }
+
+    let trait_path = &args.trait_path;
+
+    let assign_path = assign_trait(trait_path);
+
+    let (Some(method), Some(assign_method)) = (ops::method_name(trait_path), ops::method_name(&assign_path)) else {
+        return syn::Error::new_spanned(trait_path, "Unknown operator trait; expected a binary operator from `std::ops`.")
+            .to_compile_error()
+            .into();
+    };
+
+    let sig = &fn_item.sig;
+    let mut fn_args = sig.inputs.iter();
+
+    let (Some(syn::FnArg::Receiver(lhs)), Some(syn::FnArg::Typed(rhs)), None) = (fn_args.next(), fn_args.next(), fn_args.next()) else {
+        return syn::Error::new_spanned(&sig.inputs, "Requires two arguments (self: T1, rhs: T2).")
+            .to_compile_error()
+            .into();
+    };
+
+    let (lhs_type, rhs_type) = (&lhs.ty, &rhs.ty);
+
+    match &sig.output {
+        syn::ReturnType::Type(_, output) if same_type(output, lhs_type) => {}
+        syn::ReturnType::Default => {}
+        output => {
+            return syn::Error::new_spanned(output, "The output must be the type of `self` to assign it.")
+                .to_compile_error()
+                .into();
         }
     }
 
-    (comments, other_tkns)
+    let fn_generics = &sig.generics;
+    let where_clause = &fn_generics.where_clause;
+
+    let token = quote! {
+        #[opimps::impl_ops(#attr_tokens)]
+        #fn_item
+
+        #[opimps::impl_ops_assign(#assign_path)]
+        fn #assign_method #fn_generics (self: #lhs_type, rhs: #rhs_type) #where_clause {
+            *self = #trait_path::#method(&*self, rhs);
+        }
+    };
+
+    TokenStream::from(token)
+}
+
+/// Implements an assignment-based operator with a primitive value on the right-hand side.
+///
+/// Only the owned right-hand side is implemented, since borrowing a `Copy` primitive
+/// gains nothing.
+///
+/// ```
+/// pub struct Vec3 {
+///     pub x: f64,
+///     pub y: f64,
+///     pub z: f64
+/// }
+///
+/// #[opimps::impl_ops_assign_rprim(std::ops::MulAssign)]
+/// fn mul_assign(self: Vec3, rhs: f64) {
+///     self.x *= rhs;
+///     self.y *= rhs;
+///     self.z *= rhs;
+/// }
+///
+/// let mut v = Vec3 { x: 1.0, y: 2.0, z: 3.0 };
+/// v *= 2.0;
+///
+/// assert_eq!(6.0, v.z);
+/// ```
+///
+/// ```compile_fail
+/// pub struct Meters(f64);
+///
+/// #[opimps::impl_ops_assign_rprim(std::ops::AddAssign)]
+/// fn add_assign(self: Meters, rhs: f64) {
+///     self.0 += rhs;
+/// }
+///
+/// let mut m = Meters(1.0);
+///
+/// // Error: `AddAssign<&f64>` isn't implemented for `Meters`.
+/// m += &2.0;
+/// ```
+#[proc_macro_attribute]
+pub fn impl_ops_assign_rprim(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(attr as OpArgs);
+    let fn_item = parse_macro_input!(item with parse_operator_fn);
+
+    if let Err(e) = check_signature(&args, &fn_item) {
+        return e.to_compile_error().into();
+    }
+
+    if let Err(e) = check_single_trait(&args) {
+        return e.to_compile_error().into();
+    }
+
+    let trait_path = args.trait_path;
+
+    let token = quote! {
+        #[opimps::impl_op_assign(#trait_path)]
+        #fn_item
+    };
+
+    TokenStream::from(token)
+}
+
+/// Implements element-wise binary operators for a structure wrapping a `Vec`, for all
+/// permutations of owned and borrowed data.
+///
+/// The attribute is placed on the structure, which must have a single `Vec<T>` field
+/// selected with `field` (`0` by default). Elements are cloned and combined with the
+/// operator, so `T` must implement `Clone` and the trait with `Output = T`.
+///
+/// When the lengths differ, `on_mismatch = panic` (the default) panics, while
+/// `on_mismatch = result(E)` makes the output `Result<Self, E>` and returns
+/// `<E as From<(usize, usize)>>::from((lhs_len, rhs_len))`, so `E` can have generic arguments.
+///
+/// ```
+/// use std::ops::Add;
+///
+/// #[opimps::impl_vec_ops(Add, field = 0, on_mismatch = panic)]
+/// struct DVec(Vec<f64>);
+///
+/// let a = DVec(vec![1.0, 2.0]);
+/// let b = DVec(vec![3.0, 4.0]);
+///
+/// assert_eq!(vec![4.0, 6.0], (&a + &b).0);
+/// assert_eq!(vec![4.0, 6.0], (a + b).0);
+/// ```
+///
+/// ```
+/// use std::ops::Sub;
+///
+/// #[derive(Debug, PartialEq)]
+/// struct LenErr(usize, usize);
+///
+/// impl From<(usize, usize)> for LenErr {
+///     fn from((lhs, rhs): (usize, usize)) -> Self {
+///         LenErr(lhs, rhs)
+///     }
+/// }
+///
+/// #[opimps::impl_vec_ops(Sub, on_mismatch = result(LenErr))]
+/// struct DVec(Vec<f64>);
+///
+/// let a = DVec(vec![3.0, 4.0]);
+/// let b = DVec(vec![1.0]);
+///
+/// assert_eq!(Some(LenErr(2, 1)), (&a - &b).err());
+/// ```
+#[proc_macro_attribute]
+pub fn impl_vec_ops(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(attr as VecOpArgs);
+    let item_struct = parse_macro_input!(item as syn::ItemStruct);
+
+    let trait_path = &args.trait_path;
+    let field = &args.field;
+
+    let Some(method) = ops::method_name(trait_path) else {
+        return syn::Error::new_spanned(trait_path, "Unknown operator trait; expected a binary operator from `std::ops`.")
+            .to_compile_error()
+            .into();
+    };
+
+    let field_type = match single_field_type(&item_struct, field) {
+        Ok(ty) => ty,
+        Err(e) => return e.to_compile_error().into()
+    };
+
+    let Some(elem_type) = vec_element_type(field_type) else {
+        return syn::Error::new_spanned(field_type, "Field must be a `Vec<T>`.")
+            .to_compile_error()
+            .into();
+    };
+
+    let struct_name = &item_struct.ident;
+    let (_, ty_generics, _) = item_struct.generics.split_for_impl();
+    let self_type = quote! { #struct_name #ty_generics };
+
+    let mut fn_generics = item_struct.generics.clone();
+    fn_generics.make_where_clause().predicates.push(
+        syn::parse_quote! { #elem_type: Clone + #trait_path<Output = #elem_type> }
+    );
+    let where_clause = &fn_generics.where_clause;
+
+    let combined = quote! {
+        #struct_name {
+            #field: self.#field.iter()
+                .zip(rhs.#field.iter())
+                .map(|(l, r)| #trait_path::#method(l.clone(), r.clone()))
+                .collect()
+        }
+    };
+
+    let (fn_output, fn_body) = match &args.on_mismatch {
+        OnMismatch::Panic => (
+            self_type.clone(),
+            quote! {
+                let (lhs_len, rhs_len) = (self.#field.len(), rhs.#field.len());
+                if lhs_len != rhs_len {
+                    panic!("Length mismatch: {} != {}", lhs_len, rhs_len);
+                }
+                #combined
+            }
+        ),
+        OnMismatch::Result(err_type) => (
+            quote! { Result<#self_type, #err_type> },
+            quote! {
+                let (lhs_len, rhs_len) = (self.#field.len(), rhs.#field.len());
+                if lhs_len != rhs_len {
+                    return Err(<#err_type as ::core::convert::From<(usize, usize)>>::from((lhs_len, rhs_len)));
+                }
+                Ok(#combined)
+            }
+        )
+    };
+
+    let token = quote! {
+        #item_struct
+
+        #[opimps::impl_ops(#trait_path)]
+        fn #method #fn_generics (self: #self_type, rhs: #self_type) -> #fn_output #where_clause {
+            #fn_body
+        }
+    };
+
+    TokenStream::from(token)
+}
+
+/// Implements a binary operator between a newtype and the primitive it wraps, in both
+/// directions and for the borrowed newtype, wrapping the result back into the newtype.
+///
+/// The attribute is placed on the newtype, which must have a single field selected with
+/// `field` (`0` by default). The primitive `prim` defaults to the type of that field, and
+/// `wrapper`, if given, must name the newtype.
+///
+/// ```
+/// use std::ops::Add;
+///
+/// #[derive(Debug, PartialEq)]
+/// #[opimps::impl_scalar_ops(Add, wrapper = Secs, field = 0, prim = f64)]
+/// struct Secs(f64);
+///
+/// let a = Secs(1.5);
+///
+/// assert_eq!(Secs(2.5), &a + 1.0);
+/// assert_eq!(Secs(2.5), 1.0 + &a);
+/// assert_eq!(Secs(2.5), a + 1.0);
+/// ```
+#[proc_macro_attribute]
+pub fn impl_scalar_ops(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(attr as ScalarOpArgs);
+    let item_struct = parse_macro_input!(item as syn::ItemStruct);
+
+    let trait_path = &args.trait_path;
+    let field = &args.field;
+
+    let Some(method) = ops::method_name(trait_path) else {
+        return syn::Error::new_spanned(trait_path, "Unknown operator trait; expected a binary operator from `std::ops`.")
+            .to_compile_error()
+            .into();
+    };
+
+    if let Some(wrapper) = args.wrapper.as_ref().filter(|wrapper| **wrapper != item_struct.ident) {
+        return syn::Error::new_spanned(wrapper, "`wrapper` must name the structure the attribute is placed on.")
+            .to_compile_error()
+            .into();
+    }
+
+    let field_type = match single_field_type(&item_struct, field) {
+        Ok(ty) => ty,
+        Err(e) => return e.to_compile_error().into()
+    };
+    let prim = args.prim.as_ref().unwrap_or(field_type);
+
+    let struct_name = &item_struct.ident;
+    let (_, ty_generics, _) = item_struct.generics.split_for_impl();
+    let self_type = quote! { #struct_name #ty_generics };
+
+    let mut fn_generics = item_struct.generics.clone();
+    fn_generics.make_where_clause().predicates.push(
+        syn::parse_quote! { #prim: #trait_path<Output = #prim> }
+    );
+    let where_clause = &fn_generics.where_clause;
+
+    let token = quote! {
+        #item_struct
+
+        #[opimps::impl_ops_rprim(#trait_path)]
+        fn #method #fn_generics (self: #self_type, rhs: #prim) -> #self_type #where_clause {
+            #struct_name { #field: #trait_path::#method(self.#field, rhs) }
+        }
+
+        #[opimps::impl_ops_lprim(#trait_path)]
+        fn #method #fn_generics (self: #prim, rhs: #self_type) -> #self_type #where_clause {
+            #struct_name { #field: #trait_path::#method(self, rhs.#field) }
+        }
+    };
+
+    TokenStream::from(token)
+}
+
+/// Implements a binary operator combining two `Option`s, for all permutations of owned and
+/// borrowed data.
+///
+/// When both operands are `Some`, their values are combined with `inner_merge`. Otherwise,
+/// the operand that is present is returned, or `None` if neither is. The body of the function
+/// is generated, so it must be left empty. The borrowed permutations clone the values, so
+/// they require the operands to be `Clone`.
+///
+/// ```
+/// pub trait Merge<Rhs = Self> {
+///     type Output;
+///     fn merge(self, rhs: Rhs) -> Self::Output;
+/// }
+///
+/// #[derive(Clone, Debug, PartialEq)]
+/// pub struct Config {
+///     retries: u32
+/// }
+///
+/// fn combine(lhs: Config, rhs: Config) -> Config {
+///     Config { retries: lhs.retries.max(rhs.retries) }
+/// }
+///
+/// #[opimps::impl_option_ops(Merge, inner_merge = combine)]
+/// fn merge(self: Option<Config>, rhs: Option<Config>) -> Option<Config> {}
+///
+/// let a = Some(Config { retries: 3 });
+/// let b = Some(Config { retries: 5 });
+///
+/// assert_eq!(Some(Config { retries: 5 }), (&a).merge(&b));
+/// assert_eq!(Some(Config { retries: 3 }), a.merge(None));
+/// ```
+#[proc_macro_attribute]
+pub fn impl_option_ops(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(attr as OptionOpArgs);
+    let mut fn_item = parse_macro_input!(item with parse_operator_fn);
+
+    let trait_path = &args.trait_path;
+    let inner_merge = &args.inner_merge;
+
+    if !fn_item.block.stmts.is_empty() {
+        return syn::Error::new_spanned(&fn_item.block, "The body is generated by `impl_option_ops` and must be empty.")
+            .to_compile_error()
+            .into();
+    }
+
+    let (lhs, rhs) = match binary_params(&fn_item.sig) {
+        Ok(params) => params,
+        Err(e) => return e.to_compile_error().into()
+    };
+
+    let rhs_pat = &rhs.pat;
+
+    for ty in [&lhs.ty, &rhs.ty] {
+        if option_inner_type(ty).is_none() {
+            return syn::Error::new_spanned(ty, "Operands of `impl_option_ops` must be `Option<T>`.")
+                .to_compile_error()
+                .into();
+        }
+    }
+
+    fn_item.block = syn::parse_quote! {
+        {
+            match (self, #rhs_pat) {
+                (Some(l), Some(r)) => Some(#inner_merge(l, r)),
+                (Some(v), None) | (None, Some(v)) => Some(v),
+                (None, None) => None
+            }
+        }
+    };
+
+    let token = quote! {
+        #[opimps::impl_ops(#trait_path; clone_refs)]
+        #fn_item
+    };
+
+    TokenStream::from(token)
+}
+
+/// Implements a comparison trait for the permutations of owned and borrowed data.
+///
+/// The comparison methods borrow their operands, so the body sees `self` and `rhs` as
+/// references whether or not the operands are declared as borrowed. The borrowed `self`
+/// implementations forward to the owned one. Comparing two references is covered by the
+/// implementations of the standard library, and provided methods like `ne` are left to
+/// their defaults. When `rhs` is a bare type parameter, the borrowed `self` isn't
+/// implemented, as it would overlap with the standard library's comparison of references.
+///
+/// ```
+/// pub struct Meters(f64);
+/// pub struct Feet(f64);
+///
+/// #[opimps::impl_cmp_ops(PartialEq)]
+/// fn eq(self: Meters, rhs: Feet) -> bool {
+///     self.0 == rhs.0 * 0.3048
+/// }
+///
+/// let m = Meters(3.048);
+/// let f = Feet(10.0);
+///
+/// assert!(m == f);
+/// assert!(m == &f);
+/// assert!(&m == f);
+/// assert!(&m == &f);
+/// assert!(!(m != f));
+/// ```
+///
+/// `PartialOrd` requires `PartialEq` for the same operands, and its `partial_cmp`
+/// returns an `Option<Ordering>`.
+///
+/// ```
+/// use std::cmp::Ordering;
+///
+/// pub struct Version(u32, u32);
+///
+/// #[opimps::impl_cmp_ops(PartialEq)]
+/// fn eq(self: Version, rhs: Version) -> bool {
+///     (self.0, self.1) == (rhs.0, rhs.1)
+/// }
+///
+/// #[opimps::impl_cmp_ops(PartialOrd)]
+/// fn partial_cmp(self: Version, rhs: Version) -> Option<Ordering> {
+///     (self.0, self.1).partial_cmp(&(rhs.0, rhs.1))
+/// }
+///
+/// let old = Version(1, 2);
+/// let new = Version(1, 10);
+///
+/// assert!(old < new);
+/// assert!(old < &new);
+/// assert!(&old < new);
+/// ```
+#[proc_macro_attribute]
+pub fn impl_cmp_ops(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let trait_path = parse_macro_input!(attr with args::parse_trait_path);
+    let fn_item = parse_macro_input!(item with parse_operator_fn);
+
+    TokenStream::from(expand_cmp_ops(trait_path, fn_item))
+}
+
+fn expand_cmp_ops(trait_path: syn::TypePath, fn_item: syn::ItemFn) -> proc_macro2::TokenStream {
+    let Some(method) = ops::comparison_name(&trait_path) else {
+        return syn::Error::new_spanned(&trait_path, "Unknown comparison trait; expected `PartialEq` or `PartialOrd`.")
+            .to_compile_error();
+    };
+
+    let sig = &fn_item.sig;
+
+    if sig.ident != method {
+        return syn::Error::new_spanned(&sig.ident, format!("Expected the required method `{}`.", method))
+            .to_compile_error();
+    }
+
+    let mut fn_args = sig.inputs.iter();
+
+    let (Some(syn::FnArg::Receiver(lhs)), Some(syn::FnArg::Typed(rhs)), None) = (fn_args.next(), fn_args.next(), fn_args.next()) else {
+        return syn::Error::new_spanned(&sig.inputs, "Requires two arguments (self: T1, rhs: T2).")
+            .to_compile_error();
+    };
+
+    let fn_output = match output_type(sig) {
+        Ok(output) => output,
+        Err(e) => return e.to_compile_error()
+    };
+
+    // The operands are borrowed either way, so a borrowed declaration means the same.
+    let unborrowed = |ty: &syn::Type| match ty {
+        syn::Type::Reference(reference) => reference.elem.as_ref().clone(),
+        ty => ty.clone()
+    };
+
+    let lhs_type = unborrowed(&lhs.ty);
+    let rhs_type = unborrowed(&rhs.ty);
+    let rhs_pat = &rhs.pat;
+
+    let fn_name = &sig.ident;
+    let fn_generics = &sig.generics;
+    let (impl_generics, _, where_clause) = fn_generics.split_for_impl();
+    let fn_body = &fn_item.block;
+    let (cfg_tkns, other_tkns) = extract_cfgs(&fn_item.attrs);
+
+    // With a bare type parameter on the right, the borrowed `self` would overlap with core's
+    // `impl PartialEq<&B> for &A`, as the parameter could itself be a reference.
+    let lhs_forward = if type_param(&rhs_type, fn_generics).is_some() {
+        proc_macro2::TokenStream::new()
+    } else {
+        quote! {
+            #cfg_tkns
+            #[automatically_derived]
+            impl #impl_generics #trait_path<#rhs_type> for &#lhs_type #where_clause {
+                fn #fn_name (&self, rhs: &#rhs_type) -> #fn_output {
+                    <#lhs_type as #trait_path<#rhs_type>>::#fn_name(*self, rhs)
+                }
+            }
+        }
+    };
+
+    quote! {
+        #cfg_tkns
+        #[automatically_derived]
+        impl #impl_generics #trait_path<#rhs_type> for #lhs_type #where_clause {
+            #other_tkns
+            fn #fn_name (&self, #rhs_pat: &#rhs_type) -> #fn_output
+                #fn_body
+        }
+
+        #cfg_tkns
+        #[automatically_derived]
+        impl #impl_generics #trait_path<&#rhs_type> for #lhs_type #where_clause {
+            fn #fn_name (&self, rhs: &&#rhs_type) -> #fn_output {
+                <#lhs_type as #trait_path<#rhs_type>>::#fn_name(self, *rhs)
+            }
+        }
+
+        #lhs_forward
+    }
+}
+
+/// Implements a trait whose methods borrow their operands from a whole `impl` block, for the
+/// permutations of owned and borrowed data, in the same way as `impl_cmp_ops`.
+///
+/// This keeps related methods together, e.g. `partial_cmp` along with `lt`, or `eq` along
+/// with a custom `ne`. Each method takes `&self` and a borrowed `rhs`, and the
+/// implementations with a borrowed operand forward every method to the declared one.
+///
+/// ```
+/// use std::cmp::Ordering;
+///
+/// pub struct Version(u32, u32);
+///
+/// #[opimps::impl_ops_block]
+/// impl PartialEq for Version {
+///     fn eq(&self, rhs: &Version) -> bool {
+///         (self.0, self.1) == (rhs.0, rhs.1)
+///     }
+///
+///     fn ne(&self, rhs: &Version) -> bool {
+///         (self.0, self.1) != (rhs.0, rhs.1)
+///     }
+/// }
+///
+/// #[opimps::impl_ops_block]
+/// impl PartialOrd for Version {
+///     fn partial_cmp(&self, rhs: &Version) -> Option<Ordering> {
+///         (self.0, self.1).partial_cmp(&(rhs.0, rhs.1))
+///     }
+///
+///     fn lt(&self, rhs: &Version) -> bool {
+///         (self.0, self.1) < (rhs.0, rhs.1)
+///     }
+/// }
+///
+/// let (old, new) = (Version(1, 2), Version(1, 10));
+///
+/// assert!(old < new);
+/// assert!(&old < new);
+/// assert!(old != &new);
+/// assert!(&old <= &new);
+/// ```
+#[proc_macro_attribute]
+pub fn impl_ops_block(attr: TokenStream, item: TokenStream) -> TokenStream {
+    parse_macro_input!(attr as syn::parse::Nothing);
+    let item_impl = parse_macro_input!(item as syn::ItemImpl);
+
+    TokenStream::from(expand_ops_block(item_impl))
+}
+
+fn expand_ops_block(item_impl: syn::ItemImpl) -> proc_macro2::TokenStream {
+    let Some((None, trait_path, _)) = &item_impl.trait_ else {
+        return syn::Error::new_spanned(&item_impl.self_ty, "Requires a trait implementation, e.g. `impl PartialOrd for T`.")
+            .to_compile_error();
+    };
+
+    let self_type = &item_impl.self_ty;
+
+    // The `rhs` type is the generic argument of the trait, or the type of `self` when it's
+    // left to the default. `Self` would be the borrowed type in the forwarding implementations.
+    let mut trait_base = trait_path.clone();
+    let Some(segment) = trait_base.segments.last_mut() else {
+        return syn::Error::new_spanned(trait_path, "Expected a trait path.").to_compile_error();
+    };
+
+    let rhs_type = match std::mem::replace(&mut segment.arguments, syn::PathArguments::None) {
+        syn::PathArguments::AngleBracketed(args) => match (args.args.first(), args.args.len()) {
+            (Some(syn::GenericArgument::Type(rhs_type)), 1) => replace_self_in_type(rhs_type, self_type),
+            _ => {
+                return syn::Error::new_spanned(trait_path, "Expected a single `rhs` type, e.g. `PartialOrd<T>`.")
+                    .to_compile_error();
+            }
+        },
+        syn::PathArguments::None => self_type.as_ref().clone(),
+        syn::PathArguments::Parenthesized(args) => {
+            return syn::Error::new_spanned(args, "Expected a single `rhs` type, e.g. `PartialOrd<T>`.")
+                .to_compile_error();
+        }
+    };
+
+    let mut rhs_forwards = proc_macro2::TokenStream::new();
+    let mut lhs_forwards = proc_macro2::TokenStream::new();
+
+    for item in &item_impl.items {
+        let syn::ImplItem::Fn(method) = item else {
+            rhs_forwards.extend(item.to_token_stream());
+            lhs_forwards.extend(item.to_token_stream());
+            continue;
+        };
+
+        if let Err(e) = check_borrowing_method(&method.sig) {
+            return e.to_compile_error();
+        }
+
+        let fn_name = &method.sig.ident;
+        let fn_output = &method.sig.output;
+
+        // A method left out by its `cfg` is left out of the forwarding implementations as well.
+        let (method_cfgs, _) = extract_cfgs(&method.attrs);
+
+        rhs_forwards.extend(quote! {
+            #method_cfgs
+            fn #fn_name (&self, rhs: &&#rhs_type) #fn_output {
+                <#self_type as #trait_base<#rhs_type>>::#fn_name(self, *rhs)
+            }
+        });
+
+        lhs_forwards.extend(quote! {
+            #method_cfgs
+            fn #fn_name (&self, rhs: &#rhs_type) #fn_output {
+                <#self_type as #trait_base<#rhs_type>>::#fn_name(*self, rhs)
+            }
+        });
+    }
+
+    let (impl_generics, _, where_clause) = item_impl.generics.split_for_impl();
+    let (cfg_tkns, _) = extract_cfgs(&item_impl.attrs);
+
+    quote! {
+        #item_impl
+
+        #cfg_tkns
+        #[automatically_derived]
+        impl #impl_generics #trait_base<&#rhs_type> for #self_type #where_clause {
+            #rhs_forwards
+        }
+
+        #cfg_tkns
+        #[automatically_derived]
+        impl #impl_generics #trait_base<#rhs_type> for &#self_type #where_clause {
+            #lhs_forwards
+        }
+    }
+}
+
+/// Implements `Index`, or `IndexMut` when `self` is borrowed mutably.
+///
+/// The function borrows `self` and returns a reference, whose referenced type becomes the
+/// `Output` of `Index`. `IndexMut` uses the `Output` of `Index`, so both are usually
+/// implemented together.
+///
+/// ```
+/// pub struct Matrix {
+///     cols: usize,
+///     data: Vec<f64>
+/// }
+///
+/// #[opimps::impl_ops_idx]
+/// fn index(self: &Matrix, row: usize) -> &[f64] {
+///     &self.data[row * self.cols..(row + 1) * self.cols]
+/// }
+///
+/// #[opimps::impl_ops_idx]
+/// fn index_mut(self: &mut Matrix, row: usize) -> &mut [f64] {
+///     &mut self.data[row * self.cols..(row + 1) * self.cols]
+/// }
+///
+/// let mut m = Matrix { cols: 2, data: vec![1.0, 2.0, 3.0, 4.0] };
+/// m[1][0] = 5.0;
+///
+/// assert_eq!([5.0, 4.0], m[1]);
+/// ```
+#[proc_macro_attribute]
+pub fn impl_ops_idx(attr: TokenStream, item: TokenStream) -> TokenStream {
+    parse_macro_input!(attr as syn::parse::Nothing);
+    let fn_item = parse_macro_input!(item with parse_operator_fn);
+
+    let sig = &fn_item.sig;
+
+    let (lhs_ref, output_ref) = match borrowing_signature(sig) {
+        Ok(refs) => refs,
+        Err(e) => return e.to_compile_error().into()
+    };
+
+    let (Some(syn::FnArg::Typed(idx)), 2) = (sig.inputs.iter().nth(1), sig.inputs.len()) else {
+        return syn::Error::new_spanned(&sig.inputs, "Requires two arguments (self: &T, idx: Idx).")
+            .to_compile_error()
+            .into();
+    };
+
+    let fn_name = &sig.ident;
+    let fn_generics = &sig.generics;
+    let (impl_generics, _, where_clause) = fn_generics.split_for_impl();
+    let fn_body = &fn_item.block;
+    let attrs = &fn_item.attrs;
+
+    let self_type = &lhs_ref.elem;
+    let idx_type = &idx.ty;
+    let output = &output_ref.elem;
+
+    let token = if lhs_ref.mutability.is_some() {
+        quote! {
+            #[automatically_derived]
+            impl #impl_generics ::core::ops::IndexMut<#idx_type> for #self_type #where_clause {
+                #(#attrs)*
+                fn #fn_name (&mut self, #idx) -> &mut Self::Output
+                    #fn_body
+            }
+        }
+    } else {
+        quote! {
+            #[automatically_derived]
+            impl #impl_generics ::core::ops::Index<#idx_type> for #self_type #where_clause {
+                type Output = #output;
+                #(#attrs)*
+                fn #fn_name (&self, #idx) -> &Self::Output
+                    #fn_body
+            }
+        }
+    };
+
+    TokenStream::from(token)
+}
+
+/// Implements `Deref`, or `DerefMut` when `self` is borrowed mutably.
+///
+/// The function borrows `self` and returns a reference, whose referenced type becomes the
+/// `Target` of `Deref`. `DerefMut` uses the `Target` of `Deref`, so both are usually
+/// implemented together.
+///
+/// ```
+/// pub struct Name(String);
+///
+/// #[opimps::impl_deref]
+/// fn deref(self: &Name) -> &String {
+///     &self.0
+/// }
+///
+/// #[opimps::impl_deref]
+/// fn deref_mut(self: &mut Name) -> &mut String {
+///     &mut self.0
+/// }
+///
+/// let mut name = Name(String::from("Ada"));
+/// name.push_str(" Lovelace");
+///
+/// assert_eq!(12, name.len());
+/// ```
+#[proc_macro_attribute]
+pub fn impl_deref(attr: TokenStream, item: TokenStream) -> TokenStream {
+    parse_macro_input!(attr as syn::parse::Nothing);
+    let fn_item = parse_macro_input!(item with parse_operator_fn);
+
+    let sig = &fn_item.sig;
+
+    let (lhs_ref, output_ref) = match borrowing_signature(sig) {
+        Ok(refs) => refs,
+        Err(e) => return e.to_compile_error().into()
+    };
+
+    if sig.inputs.len() != 1 {
+        return syn::Error::new_spanned(&sig.inputs, "Requires a single argument (self: &T).")
+            .to_compile_error()
+            .into();
+    }
+
+    let fn_name = &sig.ident;
+    let fn_generics = &sig.generics;
+    let (impl_generics, _, where_clause) = fn_generics.split_for_impl();
+    let fn_body = &fn_item.block;
+    let attrs = &fn_item.attrs;
+
+    let self_type = &lhs_ref.elem;
+    let target = &output_ref.elem;
+
+    let token = if lhs_ref.mutability.is_some() {
+        quote! {
+            #[automatically_derived]
+            impl #impl_generics ::core::ops::DerefMut for #self_type #where_clause {
+                #(#attrs)*
+                fn #fn_name (&mut self) -> &mut Self::Target
+                    #fn_body
+            }
+        }
+    } else {
+        quote! {
+            #[automatically_derived]
+            impl #impl_generics ::core::ops::Deref for #self_type #where_clause {
+                type Target = #target;
+                #(#attrs)*
+                fn #fn_name (&self) -> &Self::Target
+                    #fn_body
+            }
+        }
+    };
+
+    TokenStream::from(token)
+}
+
+/// Implements `Sum` from a function folding an iterator, for both owned and borrowed items.
+///
+/// The function takes an iterator type parameter bounded by `Iterator<Item = A>` and
+/// implements `Sum<A>` for its return type. The borrowed items are cloned into the function,
+/// so `Sum<&A>` is implemented where `A: Clone` when `A` is generic. For a concrete `A`,
+/// which the bound can't leave out when it isn't `Clone`, `Sum<&A>` is only implemented
+/// with the `borrowed` option.
+///
+/// ```
+/// #[derive(Debug, Clone, Copy, PartialEq)]
+/// pub struct Meters(f64);
+///
+/// #[opimps::impl_iter_sum(borrowed)]
+/// fn sum<I: Iterator<Item = Meters>>(iter: I) -> Meters {
+///     Meters(iter.map(|m| m.0).sum())
+/// }
+///
+/// let legs = vec![Meters(1.5), Meters(2.5)];
+///
+/// assert_eq!(Meters(4.0), legs.iter().sum::<Meters>());
+/// assert_eq!(Meters(4.0), legs.into_iter().sum::<Meters>());
+/// ```
+#[proc_macro_attribute]
+pub fn impl_iter_sum(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(attr as IterArgs);
+    let fn_item = parse_macro_input!(item as syn::ItemFn);
+
+    TokenStream::from(expand_iter_fold(args, fn_item, quote! { ::core::iter::Sum }, "sum"))
+}
+
+/// Implements `Product` from a function folding an iterator, for both owned and borrowed
+/// items, in the same way as `impl_iter_sum`.
+///
+/// ```
+/// #[derive(Debug, Clone, PartialEq)]
+/// pub struct Factor(u32);
+///
+/// #[opimps::impl_iter_product(borrowed)]
+/// fn product<I: Iterator<Item = Factor>>(iter: I) -> Factor {
+///     Factor(iter.map(|f| f.0).product())
+/// }
+///
+/// let factors = vec![Factor(2), Factor(3), Factor(7)];
+///
+/// assert_eq!(Factor(42), factors.iter().product::<Factor>());
+/// assert_eq!(Factor(42), factors.into_iter().product::<Factor>());
+/// ```
+#[proc_macro_attribute]
+pub fn impl_iter_product(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(attr as IterArgs);
+    let fn_item = parse_macro_input!(item as syn::ItemFn);
+
+    TokenStream::from(expand_iter_fold(args, fn_item, quote! { ::core::iter::Product }, "product"))
+}
+
+fn expand_iter_fold(args: IterArgs, fn_item: syn::ItemFn, trait_path: proc_macro2::TokenStream, method: &str) -> proc_macro2::TokenStream {
+    let sig = &fn_item.sig;
+
+    let (iter_type, item_type) = match iterator_param(sig) {
+        Ok(param) => param,
+        Err(e) => return e.to_compile_error()
+    };
+
+    let fn_output = match output_type(sig) {
+        Ok(output) => output,
+        Err(e) => return e.to_compile_error()
+    };
+
+    let iter_pat = match (sig.inputs.first(), sig.inputs.len()) {
+        (Some(syn::FnArg::Typed(pat)), 1) if matches!(pat.ty.as_ref(), syn::Type::Path(path) if path.path.is_ident(iter_type)) => &pat.pat,
+        _ => {
+            return syn::Error::new_spanned(&sig.inputs, format!("Requires a single argument (iter: {}).", iter_type))
+                .to_compile_error();
+        }
+    };
+
+    // The iterator type parameter belongs to the trait method, the others to the implementation.
+    let mut impl_generics = sig.generics.clone();
+    impl_generics.params = impl_generics.params.into_iter()
+        .filter(|param| !matches!(param, syn::GenericParam::Type(param) if &param.ident == iter_type))
+        .collect();
+
+    if let Some(where_clause) = impl_generics.where_clause.as_mut() {
+        where_clause.predicates = where_clause.predicates.clone().into_iter()
+            .filter(|predicate| !bounds_ident(predicate, iter_type))
+            .collect();
+    }
+
+    let fn_name = syn::Ident::new(method, sig.ident.span());
+    let fn_body = &fn_item.block;
+    let attrs = &fn_item.attrs;
+
+    let (owned_generics, _, owned_where) = impl_generics.split_for_impl();
+
+    let owned = quote! {
+        #[automatically_derived]
+        impl #owned_generics #trait_path<#item_type> for #fn_output #owned_where {
+            #(#attrs)*
+            fn #fn_name<#iter_type: ::core::iter::Iterator<Item = #item_type>>(#iter_pat: #iter_type) -> #fn_output
+                #fn_body
+        }
+    };
+
+    // An item declared as a reference is only ever borrowed. A concrete item that isn't
+    // `Clone` would make the `Clone` bound below an error rather than leave the impl out,
+    // so borrowed concrete items are only opted into with `borrowed`.
+    let generic = impl_generics.type_params().any(|param| mentions_ident(item_type.to_token_stream(), &param.ident));
+
+    if matches!(item_type, syn::Type::Reference(_)) || !(generic || args.borrowed) {
+        return owned;
+    }
+
+    let lifetime = syn::Lifetime::new("'__opimps_item", proc_macro2::Span::call_site());
+    let mut borrowed_generics = impl_generics.clone();
+    borrowed_generics.params.insert(0, syn::parse_quote! { #lifetime });
+    borrowed_generics.make_where_clause().predicates.push(syn::parse_quote! { #item_type: ::core::clone::Clone });
+
+    let (borrowed_generics, _, borrowed_where) = borrowed_generics.split_for_impl();
+    let iter = syn::Ident::new("iter", proc_macro2::Span::mixed_site());
+
+    quote! {
+        #owned
+
+        #[automatically_derived]
+        impl #borrowed_generics #trait_path<&#lifetime #item_type> for #fn_output #borrowed_where {
+            fn #fn_name<#iter_type: ::core::iter::Iterator<Item = &#lifetime #item_type>>(#iter: #iter_type) -> #fn_output {
+                <#fn_output as #trait_path<#item_type>>::#fn_name(#iter.cloned())
+            }
+        }
+    }
+}
+
+/// Implements `From` for the return type from a function converting its argument.
+///
+/// When the argument is borrowed, e.g. `value: &A`, `From<A>` is implemented as well and
+/// borrows the value it's given, in the same way as the owned permutations of `impl_ops`.
+///
+/// ```
+/// pub struct Celsius(f64);
+/// pub struct Fahrenheit(f64);
+///
+/// #[opimps::impl_from]
+/// fn from(value: &Celsius) -> Fahrenheit {
+///     Fahrenheit(value.0 * 1.8 + 32.0)
+/// }
+///
+/// let boiling = Celsius(100.0);
+///
+/// assert_eq!(212.0, Fahrenheit::from(&boiling).0);
+/// assert_eq!(212.0, Fahrenheit::from(boiling).0);
+/// ```
+#[proc_macro_attribute]
+pub fn impl_from(attr: TokenStream, item: TokenStream) -> TokenStream {
+    parse_macro_input!(attr as syn::parse::Nothing);
+    let fn_item = parse_macro_input!(item as syn::ItemFn);
+
+    TokenStream::from(expand_conversion(fn_item, false))
+}
+
+/// Implements `TryFrom` for the success type of a function returning a `Result`, whose error
+/// type becomes the `Error` of the implementation.
+///
+/// As with `impl_from`, a borrowed argument implements the conversion from the owned value
+/// as well.
+///
+/// ```
+/// #[derive(Debug, PartialEq)]
+/// pub struct Percent(u8);
+///
+/// #[derive(Debug, PartialEq)]
+/// pub struct OutOfRange;
+///
+/// #[opimps::impl_try_from]
+/// fn try_from(value: &u32) -> Result<Percent, OutOfRange> {
+///     u8::try_from(*value).ok().filter(|value| *value <= 100).map(Percent).ok_or(OutOfRange)
+/// }
+///
+/// assert_eq!(Ok(Percent(42)), Percent::try_from(&42));
+/// assert_eq!(Err(OutOfRange), Percent::try_from(420u32));
+/// ```
+///
+/// Any other return type is rejected.
+///
+/// ```compile_fail
+/// pub struct Percent(u8);
+///
+/// #[opimps::impl_try_from]
+/// fn try_from(value: u8) -> Option<Percent> {
+///     (value <= 100).then_some(Percent(value))
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn impl_try_from(attr: TokenStream, item: TokenStream) -> TokenStream {
+    parse_macro_input!(attr as syn::parse::Nothing);
+    let fn_item = parse_macro_input!(item as syn::ItemFn);
+
+    TokenStream::from(expand_conversion(fn_item, true))
+}
+
+fn expand_conversion(fn_item: syn::ItemFn, fallible: bool) -> proc_macro2::TokenStream {
+    let sig = &fn_item.sig;
+
+    let value = match conversion_param(sig) {
+        Ok(param) => param,
+        Err(e) => return e.to_compile_error()
+    };
+
+    let fn_output = match output_type(sig) {
+        Ok(output) => output,
+        Err(e) => return e.to_compile_error()
+    };
+
+    // A fallible conversion is implemented for the success type of the `Result`, whose
+    // error type becomes the `Error` of `TryFrom`.
+    let (trait_path, fn_name, self_type, error) = if fallible {
+        let (ok_type, err_type) = match result_types(&fn_output) {
+            Ok(types) => types,
+            Err(e) => return e.to_compile_error()
+        };
+
+        (quote! { ::core::convert::TryFrom }, "try_from", ok_type, Some(quote! { type Error = #err_type; }))
+    } else {
+        (quote! { ::core::convert::From }, "from", fn_output.as_ref(), None)
+    };
+
+    let fn_name = syn::Ident::new(fn_name, sig.ident.span());
+    let (impl_generics, _, where_clause) = sig.generics.split_for_impl();
+    let fn_body = &fn_item.block;
+    let attrs = &fn_item.attrs;
+
+    let value_pat = &value.pat;
+    let value_type = &value.ty;
+
+    let token = quote! {
+        #[automatically_derived]
+        impl #impl_generics #trait_path<#value_type> for #self_type #where_clause {
+            #error
+            #(#attrs)*
+            fn #fn_name(#value_pat: #value_type) -> #fn_output
+                #fn_body
+        }
+    };
+
+    // An owned value is only implemented as is, while a borrowed one is also accepted owned
+    // when it's sized. A borrow with a lifetime can't be taken of the owned value.
+    let (owned_type, borrowed) = declared_referent(value_type);
+    let elided = matches!(value_type.as_ref(), syn::Type::Reference(reference) if reference.lifetime.is_none());
+
+    if !borrowed || !elided || is_unsized(owned_type) {
+        return token;
+    }
+
+    let owned = syn::Ident::new("value", proc_macro2::Span::mixed_site());
+
+    quote! {
+        #token
+
+        #[automatically_derived]
+        impl #impl_generics #trait_path<#owned_type> for #self_type #where_clause {
+            #error
+            fn #fn_name(#owned: #owned_type) -> #fn_output {
+                <#self_type as #trait_path<#value_type>>::#fn_name(&#owned)
+            }
+        }
+    }
+}
+
+/// Generates a test asserting that the owned and borrowed permutations of an operator agree
+/// on a sample, which is `Default::default()` unless provided. Operands must be `Clone` and
+/// the output `PartialEq`.
+fn permutation_test(
+    trait_path: &syn::TypePath,
+    fn_name: &syn::Ident,
+    lhs_type: &syn::Type,
+    rhs_type: &syn::Type,
+    sample: Option<&syn::Expr>,
+    rhs_sample: Option<&syn::Expr>
+) -> proc_macro2::TokenStream {
+    let default: syn::Expr = syn::parse_quote! { ::core::default::Default::default() };
+    let lhs_sample = sample.unwrap_or(&default);
+    let rhs_sample = rhs_sample.or(sample).unwrap_or(&default);
+
+    // Named after the trait and operands, which are unique for each implementation.
+    let name: String = format!("__opimps_test_{}_{}_{}",
+        trait_path.to_token_stream(),
+        lhs_type.to_token_stream(),
+        rhs_type.to_token_stream()
+    )
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+    let mod_name = syn::Ident::new(&name, proc_macro2::Span::call_site());
+
+    quote! {
+        #[cfg(test)]
+        #[allow(non_snake_case)]
+        mod #mod_name {
+            use super::*;
+
+            #[test]
+            pub(super) fn permutations_agree() {
+                let lhs: #lhs_type = #lhs_sample;
+                let rhs: #rhs_type = #rhs_sample;
+
+                let expected = <#lhs_type as #trait_path<#rhs_type>>::#fn_name(lhs.clone(), rhs.clone());
+
+                assert!(expected == <&#lhs_type as #trait_path<&#rhs_type>>::#fn_name(&lhs, &rhs), "`&lhs` and `&rhs` disagree");
+                assert!(expected == <#lhs_type as #trait_path<&#rhs_type>>::#fn_name(lhs.clone(), &rhs), "`lhs` and `&rhs` disagree");
+                assert!(expected == <&#lhs_type as #trait_path<#rhs_type>>::#fn_name(&lhs, rhs.clone()), "`&lhs` and `rhs` disagree");
+            }
+        }
+    }
+}
+
+/// With the `rhs_fn` option, replaces the `_` type of `rhs` with a type parameter bound by
+/// the closure signature, since closure types can't be named.
+fn generic_rhs_fn(sig: &mut syn::Signature, bound: &syn::TypeParamBound) -> syn::Result<()> {
+    let rhs = match sig.inputs.iter_mut().nth(1) {
+        Some(syn::FnArg::Typed(rhs)) => rhs,
+        _ => return Err(syn::Error::new_spanned(&sig.inputs, "Requires two arguments (self: T1, rhs: T2)."))
+    };
+
+    if !matches!(rhs.ty.as_ref(), syn::Type::Infer(_)) {
+        return Err(syn::Error::new_spanned(&rhs.ty, "`rhs_fn` requires the type of `rhs` to be `_`."));
+    }
+
+    let param = syn::Ident::new("OpimpsRhsFn", proc_macro2::Span::call_site());
+    *rhs.ty = syn::parse_quote! { #param };
+    sig.generics.params.push(syn::parse_quote! { #param: #bound });
+
+    Ok(())
+}
+
+/// Returns the path of the assignment counterpart of a binary operator, e.g. `AddAssign`
+/// for `Add`.
+fn assign_trait(trait_path: &syn::TypePath) -> syn::TypePath {
+    let mut assign_path = trait_path.clone();
+
+    if let Some(segment) = assign_path.path.segments.last_mut() {
+        segment.ident = quote::format_ident!("{}Assign", segment.ident);
+    }
+
+    assign_path
+}
+
+/// The options that the wrapper macros pass on to `impl_op`, which affect the generated
+/// implementations rather than the functions forwarded to it.
+fn forwarded_options(args: &OpArgs) -> Option<proc_macro2::TokenStream> {
+    let mut options = Vec::new();
+
+    match args.rhs_form {
+        RhsForm::Concrete => {}
+        RhsForm::Omitted => options.push(quote! { rhs_form = omitted }),
+        RhsForm::SelfType => options.push(quote! { rhs_form = self }),
+    }
+
+    if args.no_output {
+        options.push(quote! { no_output });
+    }
+
+    if let Some(output) = &args.output {
+        options.push(quote! { output = #output });
+    }
+
+    if args.emit_debug {
+        options.push(quote! { emit_debug });
+    }
+
+    (!options.is_empty()).then(|| quote! { ; #(#options),* })
+}
+
+/// With `push_field`, generates a body pushing `rhs` onto the field of `self` and returning
+/// it. The source function's body must be empty.
+fn push_body(field: &syn::Member, block: &syn::Block, rhs_pat: &syn::Pat) -> syn::Result<Box<syn::Block>> {
+    if !block.stmts.is_empty() {
+        return Err(syn::Error::new_spanned(block, "The body is generated by `push_field` and must be empty."));
+    }
+
+    let syn::Pat::Ident(rhs) = rhs_pat else {
+        return Err(syn::Error::new_spanned(rhs_pat, "`push_field` requires `rhs` to be an identifier."));
+    };
+
+    let rhs = &rhs.ident;
+    let lhs = syn::Ident::new("lhs", proc_macro2::Span::mixed_site());
+
+    Ok(Box::new(syn::parse_quote! {
+        {
+            let mut #lhs = self;
+            #lhs.#field.push(#rhs);
+            #lhs
+        }
+    }))
+}
+
+/// With `panics_on_err`, the body returns a `Result`, which is unwrapped with the given
+/// message so that the operator itself is infallible.
+fn unwrap_or_panic(message: Option<&syn::LitStr>, block: Box<syn::Block>) -> Box<syn::Block> {
+    let Some(message) = message else {
+        return block;
+    };
+
+    Box::new(syn::parse_quote! {
+        {
+            match #block {
+                Ok(value) => value,
+                Err(e) => panic!("{}: {:?}", #message, e),
+            }
+        }
+    })
+}
+
+/// With the `out_alias` option, replaces the alias in the body with `Self::Output`, or the
+/// associated type named by `output`, so that
+/// the output can be named concisely, e.g. `Out { x, y }` or `Out::new(x, y)`.
+fn alias_output(alias: Option<&syn::Ident>, output: &syn::Ident, block: Box<syn::Block>) -> syn::Result<Box<syn::Block>> {
+    let Some(alias) = alias else {
+        return Ok(block);
+    };
+
+    // The replacement is spanned on the alias, so an alias used where a path can't go is
+    // reported on it.
+    let tokens = replace_alias(block.to_token_stream(), alias, output)?;
+    syn::parse2(tokens).map_err(|e| alias_misused(e.span(), alias))
+}
+
+fn alias_misused(span: proc_macro2::Span, alias: &syn::Ident) -> syn::Error {
+    syn::Error::new(span, format!("`{}` is the alias of the output type, so it can't name anything else in the body.", alias))
+}
+
+fn replace_alias(tokens: proc_macro2::TokenStream, alias: &syn::Ident, output: &syn::Ident) -> syn::Result<proc_macro2::TokenStream> {
+    let mut replaced = proc_macro2::TokenStream::new();
+    // Paths like `module::Out` and fields like `value.Out` aren't the alias.
+    let mut qualified = false;
+    let mut joint_colon = false;
+    // Items and bindings like `struct Out;` or `let Out = ...` would shadow the alias.
+    let mut declared = false;
+
+    for token in tokens {
+        let (is_qualifier, is_joint_colon) = match &token {
+            proc_macro2::TokenTree::Punct(p) => (
+                (p.as_char() == ':' && joint_colon) || p.as_char() == '.',
+                p.as_char() == ':' && p.spacing() == proc_macro2::Spacing::Joint
+            ),
+            _ => (false, false)
+        };
+        let is_declaration = matches!(&token, proc_macro2::TokenTree::Ident(ident) if [
+            "struct", "enum", "union", "trait", "type", "fn", "mod", "let", "ref"
+        ].iter().any(|keyword| ident == keyword));
+
+        match token {
+            proc_macro2::TokenTree::Ident(ident) if &ident == alias && declared => {
+                return Err(alias_misused(ident.span(), alias));
+            }
+            proc_macro2::TokenTree::Ident(ident) if &ident == alias && !qualified => {
+                replaced.extend(quote::quote_spanned! { ident.span() => Self::#output });
+            }
+            proc_macro2::TokenTree::Group(group) => {
+                let mut new_group = proc_macro2::Group::new(group.delimiter(), replace_alias(group.stream(), alias, output)?);
+                new_group.set_span(group.span());
+                replaced.extend([proc_macro2::TokenTree::Group(new_group)]);
+            }
+            token => replaced.extend([token])
+        }
+
+        qualified = is_qualifier;
+        joint_colon = is_joint_colon;
+        declared = is_declaration;
+    }
+
+    Ok(replaced)
+}
+
+/// Returns the references borrowed by `self` and returned by a method like `Index::index`
+/// or `Deref::deref`, which must be equally mutable.
+fn borrowing_signature(sig: &syn::Signature) -> syn::Result<(&syn::TypeReference, &syn::TypeReference)> {
+    let Some(syn::FnArg::Receiver(lhs)) = sig.inputs.first() else {
+        return Err(syn::Error::new_spanned(sig, "The first argument must be `self`."));
+    };
+
+    let syn::ReturnType::Type(_, output) = &sig.output else {
+        return Err(syn::Error::new_spanned(sig, "Function must return a reference."));
+    };
+
+    let (lhs_ref, output_ref) = (referenced(&lhs.ty)?, referenced(output)?);
+
+    if lhs_ref.mutability.is_some() != output_ref.mutability.is_some() {
+        return Err(syn::Error::new_spanned(output_ref, "The returned reference must be as mutable as `self`."));
+    }
+
+    Ok((lhs_ref, output_ref))
+}
+
+/// Splits `Result<T, E>` into `T` and `E`.
+fn result_types(ty: &syn::Type) -> syn::Result<(&syn::Type, &syn::Type)> {
+    let error = || syn::Error::new_spanned(ty, "The return type must be a `Result<T, E>`.");
+
+    let syn::Type::Path(path) = ty else {
+        return Err(error());
+    };
+
+    let segment = path.path.segments.last().filter(|segment| segment.ident == "Result").ok_or_else(error)?;
+    let syn::PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return Err(error());
+    };
+
+    match (args.args.first(), args.args.get(1), args.args.len()) {
+        (Some(syn::GenericArgument::Type(ok_type)), Some(syn::GenericArgument::Type(err_type)), 2) => Ok((ok_type, err_type)),
+        _ => Err(error())
+    }
+}
+
+/// Whether the type is known to be unsized, like `str`, `[T]` or `dyn Trait`.
+fn is_unsized(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Slice(_) | syn::Type::TraitObject(_) => true,
+        syn::Type::Path(path) => path.qself.is_none() && path.path.is_ident("str"),
+        syn::Type::Paren(paren) => is_unsized(&paren.elem),
+        _ => false
+    }
+}
+
+/// Checks that a method takes `&self` and a borrowed `rhs`, so that it can be forwarded.
+fn check_borrowing_method(sig: &syn::Signature) -> syn::Result<()> {
+    let mut fn_args = sig.inputs.iter();
+
+    match (fn_args.next(), fn_args.next(), fn_args.next()) {
+        (Some(syn::FnArg::Receiver(lhs)), Some(syn::FnArg::Typed(rhs)), None)
+            if lhs.reference.is_some() && lhs.mutability.is_none() && matches!(rhs.ty.as_ref(), syn::Type::Reference(_)) => Ok(()),
+        _ => Err(syn::Error::new_spanned(sig, "Each method must take `&self` and a borrowed `rhs`, e.g. `fn lt(&self, rhs: &T) -> bool`."))
+    }
+}
+
+/// Takes the single argument of a conversion, which isn't a receiver.
+fn conversion_param(sig: &syn::Signature) -> syn::Result<&syn::PatType> {
+    match (sig.inputs.first(), sig.inputs.len()) {
+        (Some(syn::FnArg::Typed(value)), 1) => Ok(value),
+        _ => Err(syn::Error::new_spanned(sig, "Requires a single argument (value: T)."))
+    }
+}
+
+/// Finds the type parameter bounded by `Iterator<Item = A>`, either inline or in the where
+/// clause, and returns it with the item type `A`. It can't have any other bound, which the
+/// trait method wouldn't allow.
+fn iterator_param(sig: &syn::Signature) -> syn::Result<(&syn::Ident, &syn::Type)> {
+    let where_predicates = sig.generics.where_clause.iter().flat_map(|clause| &clause.predicates);
+
+    for param in sig.generics.type_params() {
+        let bounds: Vec<_> = param.bounds.iter()
+            .chain(where_predicates.clone()
+                .filter(|predicate| bounds_ident(predicate, &param.ident))
+                .filter_map(|predicate| match predicate {
+                    syn::WherePredicate::Type(predicate) => Some(&predicate.bounds),
+                    _ => None
+                })
+                .flatten())
+            .collect();
+
+        let Some(item_type) = bounds.iter().find_map(|bound| iterator_item(bound)) else {
+            continue;
+        };
+
+        if bounds.len() > 1 {
+            return Err(syn::Error::new_spanned(&param.ident, "The iterator can only be bounded by `Iterator<Item = T>`."));
+        }
+
+        return Ok((&param.ident, item_type));
+    }
+
+    Err(syn::Error::new_spanned(sig, "Requires an iterator type parameter like `I: Iterator<Item = T>`."))
+}
+
+/// Returns `T` if the bound is `Iterator<Item = T>`.
+fn iterator_item(bound: &syn::TypeParamBound) -> Option<&syn::Type> {
+    let syn::TypeParamBound::Trait(bound) = bound else {
+        return None;
+    };
+
+    let segment = bound.path.segments.last().filter(|segment| segment.ident == "Iterator")?;
+    let syn::PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+
+    args.args.iter().find_map(|arg| match arg {
+        syn::GenericArgument::AssocType(assoc) if assoc.ident == "Item" => Some(&assoc.ty),
+        _ => None
+    })
+}
+
+/// Whether the where predicate bounds the type parameter itself, e.g. `I: Iterator`.
+fn bounds_ident(predicate: &syn::WherePredicate, ident: &syn::Ident) -> bool {
+    match predicate {
+        syn::WherePredicate::Type(predicate) => matches!(&predicate.bounded_ty, syn::Type::Path(path) if path.path.is_ident(ident)),
+        _ => false
+    }
+}
+
+fn referenced(ty: &syn::Type) -> syn::Result<&syn::TypeReference> {
+    match ty {
+        syn::Type::Reference(reference) => Ok(reference),
+        _ => Err(syn::Error::new_spanned(ty, "Expected a reference, e.g. `&T` or `&mut T`."))
+    }
+}
+
+/// Returns the referenced type `T` if the receiver is `self: &mut T` and the function
+/// returns `&mut Self`.
+fn fluent_receiver<'a>(lhs_type: &'a syn::Type, fn_output: &syn::Type) -> Option<&'a syn::Type> {
+    let (syn::Type::Reference(lhs_ref), syn::Type::Reference(output_ref)) = (lhs_type, fn_output) else {
+        return None;
+    };
+
+    let returns_self = match output_ref.elem.as_ref() {
+        syn::Type::Path(path) => path.qself.is_none() && path.path.is_ident("Self"),
+        _ => false
+    };
+
+    if lhs_ref.mutability.is_some() && output_ref.mutability.is_some() && returns_self {
+        Some(&lhs_ref.elem)
+    } else {
+        None
+    }
+}
+
+/// Ties an output reference without a lifetime to a borrowed `self`, as for the methods of
+/// an `impl`, since `type Output` can't leave it out. A `self` borrowed without a lifetime
+/// gets one.
+fn tie_output_to_receiver(lhs_type: &mut syn::Type, fn_output: &mut syn::Type, generics: &mut syn::Generics) -> syn::Result<()> {
+    let syn::Type::Reference(output_ref) = fn_output else {
+        return Ok(());
+    };
+
+    if output_ref.lifetime.is_some() {
+        return Ok(());
+    }
+
+    let syn::Type::Reference(lhs_ref) = lhs_type else {
+        return Err(syn::Error::new_spanned(output_ref, "The output reference needs the lifetime of a borrowed `self`, e.g. `fn f<'a>(self: &'a A, rhs: B) -> &'a C`, or `impl_op_ref` to borrow it from `rhs`."));
+    };
+
+    let lifetime = match &lhs_ref.lifetime {
+        Some(lifetime) => lifetime.clone(),
+        None => {
+            let lifetime = syn::Lifetime::new("'__opimps_self", proc_macro2::Span::call_site());
+            generics.params.insert(0, syn::GenericParam::Lifetime(syn::LifetimeParam::new(lifetime.clone())));
+            lhs_ref.lifetime = Some(lifetime.clone());
+            lifetime
+        }
+    };
+
+    output_ref.lifetime = Some(lifetime);
+    Ok(())
+}
+
+/// Gives the reference an explicit `lifetime`, erroring if it isn't a reference or already
+/// has a different lifetime.
+fn set_reference_lifetime(ty: &mut syn::Type, lifetime: &syn::Lifetime) -> syn::Result<()> {
+    let syn::Type::Reference(reference) = ty else {
+        return Err(syn::Error::new_spanned(ty, format!("Expected a reference with the lifetime `{}`.", lifetime)));
+    };
+
+    match &reference.lifetime {
+        None => {
+            reference.lifetime = Some(lifetime.clone());
+            Ok(())
+        }
+        Some(existing) if existing == lifetime => Ok(()),
+        Some(existing) => Err(syn::Error::new_spanned(existing, format!("Expected the lifetime `{}`.", lifetime)))
+    }
+}
+
+/// Returns whether the type is one of the type parameters of `generics` that doesn't appear
+/// in `other`.
+fn is_free_type_param(ty: &syn::Type, generics: &syn::Generics, other: &syn::Type) -> bool {
+    type_param(ty, generics).is_some_and(|ident| !mentions_ident(other.to_token_stream(), ident))
+}
+
+/// Returns the name of the type parameter if the type is a bare type parameter, e.g. `T`.
+fn type_param<'a>(ty: &'a syn::Type, generics: &syn::Generics) -> Option<&'a syn::Ident> {
+    let syn::Type::Path(path) = ty else {
+        return None;
+    };
+
+    path.path.get_ident()
+        .filter(|ident| path.qself.is_none() && generics.type_params().any(|param| &param.ident == *ident))
+}
+
+/// Replaces `Self` in the generics of the source function with the type of `self`, so that
+/// bounds like `R: Into<Self>` keep their meaning in the permutations borrowing `self`.
+fn replace_self_in_generics(mut generics: syn::Generics, self_type: &syn::Type) -> syn::Generics {
+    let params = replace_self(generics.params.to_token_stream(), self_type);
+    generics.params = syn::parse_quote!(#params);
+
+    if let Some(where_clause) = &generics.where_clause {
+        let where_clause = replace_self(where_clause.to_token_stream(), self_type);
+        generics.where_clause = Some(syn::parse_quote!(#where_clause));
+    }
+
+    generics
+}
+
+/// Replaces `Self` in the output with the type of `self`, so that it isn't the borrowed type
+/// in the permutations implemented for `&T`. A fluent `&mut Self` is left for `impl_op`,
+/// where it's already `T`.
+fn output_of_self(fn_output: Box<syn::Type>, lhs_type: &syn::Type) -> Box<syn::Type> {
+    if fluent_receiver(lhs_type, &fn_output).is_some() {
+        return fn_output;
+    }
+
+    Box::new(replace_self_in_type(&fn_output, declared_referent(lhs_type).0))
+}
+
+fn replace_self_in_type(ty: &syn::Type, self_type: &syn::Type) -> syn::Type {
+    let ty = replace_self(ty.to_token_stream(), self_type);
+    syn::parse_quote!(#ty)
+}
+
+fn replace_self(tokens: proc_macro2::TokenStream, self_type: &syn::Type) -> proc_macro2::TokenStream {
+    tokens.into_iter().map(|token| match token {
+        proc_macro2::TokenTree::Ident(ident) if ident == "Self" => self_type.to_token_stream(),
+        proc_macro2::TokenTree::Group(group) => {
+            let mut new_group = proc_macro2::Group::new(group.delimiter(), replace_self(group.stream(), self_type));
+            new_group.set_span(group.span());
+            proc_macro2::TokenTree::Group(new_group).into()
+        }
+        token => token.into()
+    }).collect()
+}
+
+/// Returns the referenced type of an operand declared as a shared reference, e.g. `B` for
+/// `&B` or `&'a B`, and whether it was one.
+fn declared_referent(ty: &syn::Type) -> (&syn::Type, bool) {
+    match ty {
+        syn::Type::Reference(reference) if reference.mutability.is_none() => (&reference.elem, true),
+        ty => (ty, false)
+    }
+}
+
+/// Moves the `unsafe` of an `unsafe fn` onto its body, as the method of an operator trait
+/// can't be `unsafe` itself.
+fn unsafe_block(mut fn_item: syn::ItemFn) -> syn::ItemFn {
+    if let Some(unsafety) = fn_item.sig.unsafety.take() {
+        let block = &fn_item.block;
+        fn_item.block = syn::parse_quote! { { #unsafety #block } };
+    }
+
+    fn_item
+}
+
+/// With further traits, expands the function for each of them, resolving the method name per
+/// trait. The documentation only goes on the implementations of the first trait.
+fn for_each_trait(
+    mut args: OpArgs,
+    fn_item: syn::ItemFn,
+    expand: fn(OpArgs, syn::ItemFn) -> proc_macro2::TokenStream
+) -> proc_macro2::TokenStream {
+    let fn_item = unsafe_block(fn_item);
+    let fn_name = fn_item.sig.ident.clone();
+    let more_traits = std::mem::take(&mut args.more_traits);
+
+    if let Err(e) = check_distinct_traits(&args.trait_path, &more_traits) {
+        return e.to_compile_error();
+    }
+    let mut token = for_each_rhs_type(args.clone(), fn_item.clone(), expand);
+
+    for trait_path in more_traits {
+        let mut args = args.clone();
+        args.trait_path = trait_path;
+
+        let mut fn_item = fn_item.clone();
+        fn_item.attrs.retain(|attr| !attr.path().is_ident("doc"));
+
+        token.extend(for_each_rhs_type(args, fn_item, expand));
+    }
+
+    debug_expansion(args.emit_debug, &fn_name, &token);
+    token
+}
+
+/// With `emit_debug`, prints the expansion of the function while compiling.
+fn debug_expansion(emit_debug: bool, fn_name: &syn::Ident, token: &proc_macro2::TokenStream) {
+    if emit_debug {
+        eprintln!("opimps: `{}` expands to\n{}\n", fn_name, token);
+    }
+}
+
+/// With a list of `rhs` types, expands the function for each of them in place of the
+/// declared type, sharing the body. The documentation only goes on the first expansion.
+fn for_each_rhs_type(
+    args: OpArgs,
+    fn_item: syn::ItemFn,
+    expand: fn(OpArgs, syn::ItemFn) -> proc_macro2::TokenStream
+) -> proc_macro2::TokenStream {
+    // The list stays on the arguments, for the macros borrowing `rhs` to check it.
+    let rhs_types = args.rhs_types.clone();
+
+    if rhs_types.is_empty() {
+        return expand(args, fn_item);
+    }
+
+    if let Err(e) = check_distinct_types(&rhs_types) {
+        return e.to_compile_error();
+    }
+
+    let mut token = proc_macro2::TokenStream::new();
+
+    for (i, rhs_type) in rhs_types.into_iter().enumerate() {
+        let mut fn_item = fn_item.clone();
+
+        if let Some(syn::FnArg::Typed(rhs)) = fn_item.sig.inputs.iter_mut().nth(1) {
+            *rhs.ty = rhs_type;
+        }
+
+        if i > 0 {
+            fn_item.attrs.retain(|attr| !attr.path().is_ident("doc"));
+        }
+
+        token.extend(expand(args.clone(), fn_item));
+    }
+
+    token
+}
+
+/// Checks that none of the types is listed twice, which would implement it twice.
+fn check_distinct_types(types: &[syn::Type]) -> syn::Result<()> {
+    for (i, ty) in types.iter().enumerate() {
+        if types[..i].iter().any(|other| same_type(other, ty)) {
+            return Err(syn::Error::new_spanned(ty, "This type is already listed."));
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks that a trait isn't listed twice, which would implement it twice.
+fn check_distinct_traits(trait_path: &syn::TypePath, more_traits: &[syn::TypePath]) -> syn::Result<()> {
+    let same_trait = |a: &syn::TypePath, b: &syn::TypePath| a.to_token_stream().to_string() == b.to_token_stream().to_string();
+
+    for (i, other) in more_traits.iter().enumerate() {
+        if same_trait(trait_path, other) || more_traits[..i].iter().any(|prev| same_trait(prev, other)) {
+            return Err(syn::Error::new_spanned(other, "This trait is already listed."));
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks that a listed `rhs` type isn't the borrowed form of the `rhs` type, e.g. `&f64`
+/// with `f64`, as its permutations borrowing `rhs` already implement it.
+fn check_borrowed_rhs_unlisted(rhs_types: &[syn::Type], rhs_type: &syn::Type) -> syn::Result<()> {
+    let borrowed = rhs_types.iter().find(|ty| match ty {
+        syn::Type::Reference(reference) => reference.mutability.is_none() && same_type(&reference.elem, rhs_type),
+        _ => false
+    });
+
+    match borrowed {
+        Some(ty) => Err(syn::Error::new_spanned(ty, format!(
+            "This type is already implemented by the permutations borrowing `{}`.",
+            rhs_type.to_token_stream()
+        ))),
+        None => Ok(())
+    }
+}
+
+/// Returns whether the type is a `Box`, e.g. `Box<Node>` or `std::boxed::Box<Node>`.
+fn is_boxed(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Path(path) if path.qself.is_none() => path.path.segments.last()
+            .is_some_and(|segment| segment.ident == "Box" && matches!(segment.arguments, syn::PathArguments::AngleBracketed(_))),
+        _ => false
+    }
+}
+
+/// Returns whether the type is one of the primitive types, e.g. `f64` or `bool`.
+fn is_primitive(ty: &syn::Type) -> bool {
+    const PRIMITIVES: &[&str] = &[
+        "i8", "i16", "i32", "i64", "i128", "isize",
+        "u8", "u16", "u32", "u64", "u128", "usize",
+        "f32", "f64", "bool", "char",
+    ];
+
+    match ty {
+        syn::Type::Path(path) if path.qself.is_none() => path.path.get_ident()
+            .is_some_and(|ident| PRIMITIVES.iter().any(|prim| ident == prim)),
+        _ => false
+    }
+}
+
+/// Returns whether the types are written identically.
+fn same_type(a: &syn::Type, b: &syn::Type) -> bool {
+    a.to_token_stream().to_string() == b.to_token_stream().to_string()
+}
+
+/// Returns whether the tokens contain the identifier, including within groups.
+fn mentions_ident(tokens: proc_macro2::TokenStream, ident: &syn::Ident) -> bool {
+    tokens.into_iter().any(|token| match token {
+        proc_macro2::TokenTree::Ident(i) => &i == ident,
+        proc_macro2::TokenTree::Group(group) => mentions_ident(group.stream(), ident),
+        _ => false
+    })
+}
+
+/// Returns the type of the only field of the structure, which must be the `field`.
+fn single_field_type<'a>(item_struct: &'a syn::ItemStruct, field: &syn::Member) -> syn::Result<&'a syn::Type> {
+    let mut fields = item_struct.fields.iter().enumerate();
+
+    match (fields.next(), fields.next()) {
+        (Some((index, f)), None) if match (&f.ident, field) {
+            (Some(ident), syn::Member::Named(name)) => ident == name,
+            (None, syn::Member::Unnamed(idx)) => idx.index as usize == index,
+            _ => false
+        } => Ok(&f.ty),
+        _ => Err(syn::Error::new_spanned(&item_struct.fields, "Structure must have a single field matching `field`."))
+    }
+}
+
+/// Returns `T` if the type is an `Option<T>`.
+fn option_inner_type(ty: &syn::Type) -> Option<&syn::Type> {
+    generic_argument_of(ty, "Option")
+}
+
+/// Returns `T` if the type is a `Vec<T>`.
+fn vec_element_type(ty: &syn::Type) -> Option<&syn::Type> {
+    generic_argument_of(ty, "Vec")
+}
+
+/// Returns `T` if the type is `Name<T>`, with `Name` as the last segment of its path.
+fn generic_argument_of<'a>(ty: &'a syn::Type, name: &str) -> Option<&'a syn::Type> {
+    let syn::Type::Path(path) = ty else {
+        return None;
+    };
+
+    let segment = path.path.segments.last()?;
+    if segment.ident != name {
+        return None;
+    }
+
+    match &segment.arguments {
+        syn::PathArguments::AngleBracketed(args) if args.args.len() == 1 => match args.args.first() {
+            Some(syn::GenericArgument::Type(elem)) => Some(elem),
+            _ => None
+        },
+        _ => None
+    }
+}
+
+/// Separates the `cfg` attributes of the source function from its other attributes.
+///
+/// A `cfg_attr` is kept with the other attributes on the method, since the attributes it
+/// conditionally adds, like `inline`, apply to the method rather than to the `impl`.
+fn extract_cfgs(attrs: &[Attribute]) -> (proc_macro2::TokenStream, proc_macro2::TokenStream) {
+    let mut cfg_tkns = proc_macro2::TokenStream::new();
+    let mut other_tkns = proc_macro2::TokenStream::new();
+
+    for attr in attrs.iter() {
+        if attr.path().is_ident("cfg") {
+            cfg_tkns.extend(attr.to_token_stream());
+        } else {
+            other_tkns.extend(attr.to_token_stream());
+        }
+    }
+
+    (cfg_tkns, other_tkns)
+}
+
+fn extract_comments(attrs: &[Attribute]) -> (proc_macro2::TokenStream, proc_macro2::TokenStream) {
+    let mut other_tkns = proc_macro2::TokenStream::new();
+    let mut comments = proc_macro2::TokenStream::new();
+
+    for attr in attrs.iter() {
+        if attr.path().is_ident("doc") {
+            comments.extend(attr.to_token_stream());
+        } else {
+            other_tkns.extend(attr.to_token_stream());
+        }
+    }
+
+    (comments, other_tkns)
+}
+
+/// Copies the doc comments with their code examples marked `ignore`, so that documentation
+/// repeated across generated implementations only runs its examples once.
+fn ignore_doc_examples(attrs: &[Attribute]) -> proc_macro2::TokenStream {
+    let mut comments = proc_macro2::TokenStream::new();
+    let mut in_example = false;
+
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("doc")) {
+        let doc = match &attr.meta {
+            syn::Meta::NameValue(syn::MetaNameValue {
+                value: syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(doc), .. }), ..
+            }) => doc,
+            _ => {
+                comments.extend(attr.to_token_stream());
+                continue;
+            }
+        };
+
+        let lines: Vec<String> = doc.value().split('\n').map(|line| {
+            let fence = line.trim_start();
+            if !fence.starts_with("```") {
+                return line.to_string();
+            }
+
+            in_example = !in_example;
+            let info = fence.trim_start_matches('`').trim();
+            if !in_example || info.split(',').any(|tag| tag.trim() == "ignore") {
+                return line.to_string();
+            }
+
+            let indent = &line[..line.len() - fence.len()];
+            if info.is_empty() {
+                format!("{}```ignore", indent)
+            } else {
+                format!("{}```{},ignore", indent, info)
+            }
+        }).collect();
+
+        let doc = syn::LitStr::new(&lines.join("\n"), doc.span());
+        comments.extend(quote! { #[doc = #doc] });
+    }
+
+    comments
+}
+
+/// The name of the trait method, which is looked up for the known operators so that the
+/// function can be named freely. Otherwise, it's the name of the function.
+fn trait_method(trait_path: &syn::TypePath, fn_name: syn::Ident) -> syn::Ident {
+    ops::method_name(trait_path).unwrap_or(fn_name)
+}
+
+/// Takes the receiver of a unary operator, erroring on the signature when it's missing.
+fn unary_param(sig: &syn::Signature) -> syn::Result<syn::Receiver> {
+    match sig.inputs.first() {
+        Some(lhs) => receiver(lhs),
+        None => Err(syn::Error::new_spanned(sig, "Function definition requires an argument (self: T)."))
+    }
+}
+
+/// Takes the two parameters of a binary operator, erroring on the signature when one is missing.
+fn binary_params(sig: &syn::Signature) -> syn::Result<(syn::Receiver, syn::PatType)> {
+    let mut params = sig.inputs.iter();
+
+    match (params.next(), params.next()) {
+        (Some(lhs), Some(syn::FnArg::Typed(rhs))) => Ok((receiver(lhs)?, rhs.clone())),
+        (Some(_), Some(rhs)) => Err(syn::Error::new_spanned(rhs, "The second parameter must be typed like `rhs: T`.")),
+        _ => Err(syn::Error::new_spanned(sig, "Requires two arguments (self: T1, rhs: T2)."))
+    }
+}
+
+/// Takes the receiver from the first parameter, erroring on it when it isn't `self`.
+fn receiver(param: &syn::FnArg) -> syn::Result<syn::Receiver> {
+    match param {
+        // The type implementing the trait is only known from an explicit `self: T`.
+        syn::FnArg::Receiver(lhs) if lhs.colon_token.is_none() => {
+            Err(syn::Error::new_spanned(param, "The receiver must be typed, e.g. `self: &mut T` rather than `&mut self`."))
+        }
+        syn::FnArg::Receiver(lhs) => Ok(lhs.clone()),
+        syn::FnArg::Typed(_) => Err(syn::Error::new_spanned(param, "The first parameter must be a receiver like `self: T`."))
+    }
+}
+
+/// Takes the return type of an operator, erroring on the signature when it's missing.
+fn output_type(sig: &syn::Signature) -> syn::Result<Box<syn::Type>> {
+    match &sig.output {
+        syn::ReturnType::Type(_, output) => Ok(output.clone()),
+        syn::ReturnType::Default => Err(missing_output(sig))
+    }
+}
+
+/// Takes the return type of a binary operator. When it's omitted, the output is the type of
+/// `self`, which mustn't be generic so that the default is unambiguous.
+fn binary_output_type(sig: &syn::Signature) -> syn::Result<Box<syn::Type>> {
+    let (syn::ReturnType::Default, Some(syn::FnArg::Receiver(lhs))) = (&sig.output, sig.inputs.first()) else {
+        return output_type(sig);
+    };
+
+    let lhs_type = match lhs.ty.as_ref() {
+        syn::Type::Reference(reference) => reference.elem.as_ref(),
+        ty => ty
+    };
+
+    let generic = sig.generics.type_params()
+        .any(|param| mentions_ident(lhs_type.to_token_stream(), &param.ident));
+
+    match lhs_type {
+        syn::Type::Path(_) if !generic => Ok(Box::new(lhs_type.clone())),
+        _ => Err(syn::Error::new_spanned(sig, "The return type can only be omitted when the type of `self` isn't generic; write `-> T`."))
+    }
+}
+
+fn missing_output(sig: &syn::Signature) -> syn::Error {
+    syn::Error::new_spanned(sig, "The operator method needs an explicit return type like `-> T`.")
+}
+
+/// Checks that an assignment operator doesn't return anything, as the trait method returns `()`.
+fn check_assign_output(sig: &syn::Signature) -> syn::Result<()> {
+    match &sig.output {
+        syn::ReturnType::Default => Ok(()),
+        output => Err(syn::Error::new_spanned(output, "Assignment operators can't have a return type."))
+    }
+}
+
+/// Parses the source function, pointing out when the attribute was placed on another item.
+fn parse_operator_fn(input: syn::parse::ParseStream) -> syn::Result<syn::ItemFn> {
+    match input.fork().parse::<syn::Item>() {
+        Ok(syn::Item::Fn(_)) | Err(_) => input.parse(),
+        Ok(_) => Err(syn::Error::new(input.span(), "opimps attributes must be placed on a free `fn` with a `self: T` first parameter."))
+    }
+}
+
+/// Checks the parts of the source function that `opimps` can't make sense of.
+fn check_signature(args: &OpArgs, fn_item: &syn::ItemFn) -> syn::Result<()> {
+    check_visibility(args, fn_item)?;
+    check_method_name(args, &fn_item.sig.ident)?;
+    check_operand_types(fn_item)?;
+    check_constrained_generics(&fn_item.sig, &args.trait_path)?;
+    check_nameable_output(&fn_item.sig)
+}
+
+/// The output becomes the associated `Output` type, where `impl Trait` isn't allowed.
+fn check_nameable_output(sig: &syn::Signature) -> syn::Result<()> {
+    let syn::ReturnType::Type(_, output) = &sig.output else {
+        return Ok(());
+    };
+
+    if mentions_ident(output.to_token_stream(), &syn::Ident::new("impl", proc_macro2::Span::call_site())) {
+        return Err(syn::Error::new_spanned(output, "The output can't be an `impl Trait`, as it becomes the associated `Output` type. Return a `Box<dyn Trait>` or a type that can be named instead."));
+    }
+
+    Ok(())
+}
+
+/// Returns whether the trait path carries its generic arguments, e.g. `Op<3>`, so that the
+/// `rhs` type isn't appended to it.
+fn has_generic_args(trait_path: &syn::TypePath) -> bool {
+    trait_path.path.segments.last()
+        .is_some_and(|segment| matches!(segment.arguments, syn::PathArguments::AngleBracketed(_)))
+}
+
+/// A trait path with generic arguments is the same trait for every permutation, so only the
+/// macros generating a single implementation accept it.
+fn check_trait_without_generics(trait_path: &syn::TypePath) -> syn::Result<()> {
+    if has_generic_args(trait_path) {
+        Err(syn::Error::new_spanned(trait_path, "A trait path with generic arguments can only be implemented by `impl_op` or `impl_op_assign`, as the permutations would all implement the same trait."))
+    } else {
+        Ok(())
+    }
+}
+
+/// The generics of the function become those of the implementation, which can only be
+/// generic over the type and const parameters that its operand types use. A parameter only
+/// used in the bounds or the output would be rejected as unconstrained, unless the generic
+/// arguments of the trait path use it.
+fn check_constrained_generics(sig: &syn::Signature, trait_path: &syn::TypePath) -> syn::Result<()> {
+    let operand_types: proc_macro2::TokenStream = sig.inputs.iter()
+        .map(|input| match input {
+            syn::FnArg::Receiver(receiver) => receiver.ty.to_token_stream(),
+            syn::FnArg::Typed(pat) => pat.ty.to_token_stream()
+        })
+        .chain(has_generic_args(trait_path).then(|| trait_path.to_token_stream()))
+        .collect();
+
+    for param in &sig.generics.params {
+        let ident = match param {
+            syn::GenericParam::Type(param) => &param.ident,
+            syn::GenericParam::Const(param) => &param.ident,
+            syn::GenericParam::Lifetime(_) => continue
+        };
+
+        if !mentions_ident(operand_types.clone(), ident) {
+            return Err(syn::Error::new_spanned(ident, format!(
+                "`{}` isn't used by the operand types, so the implementation can't be generic over it. Use it in the type of `self` or `rhs`, e.g. through a `PhantomData` field.",
+                ident
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Only the binary operator macros can implement several traits from one function.
+fn check_single_trait(args: &OpArgs) -> syn::Result<()> {
+    match args.more_traits.first() {
+        Some(trait_path) => Err(syn::Error::new_spanned(trait_path, "Only one trait can be implemented by this macro.")),
+        None => Ok(())
+    }
+}
+
+/// The function can be named freely for the known operators, but a function named after the
+/// method of another operator, e.g. `fn div` for `Mul`, is most likely a mistake. An operator
+/// and its assignment, like `Sub` and `sub_assign`, go together in `impl_ops_from_assign`.
+fn check_method_name(args: &OpArgs, fn_name: &syn::Ident) -> syn::Result<()> {
+    let (Some(method), Some(operator)) = (ops::method_name(&args.trait_path), ops::operator_name(fn_name)) else {
+        return Ok(());
+    };
+
+    let unassigned = |name: &str| name.trim_end_matches("_assign").to_owned();
+
+    if unassigned(&method.to_string()) == unassigned(&fn_name.to_string()) || !args.more_traits.is_empty() {
+        return Ok(());
+    }
+
+    Err(syn::Error::new_spanned(fn_name, format!(
+        "`{}` is the method of `{}`, but the implemented trait is `{}`, whose method is `{}`.",
+        fn_name,
+        operator,
+        args.trait_path.to_token_stream(),
+        method
+    )))
+}
+
+/// Operator implementations inherit the visibility of their trait, so a visibility on the
+/// source function has no effect. With the `strict` option, this is reported as an error.
+fn check_visibility(args: &OpArgs, fn_item: &syn::ItemFn) -> syn::Result<()> {
+    match &fn_item.vis {
+        syn::Visibility::Inherited => Ok(()),
+        vis if args.strict => Err(syn::Error::new_spanned(
+            vis,
+            "Operator implementations inherit the visibility of the trait; the visibility of this function has no effect."
+        )),
+        _ => Ok(())
+    }
+}
+
+/// Rejects operand types that are macro invocations, for the reason given in the `impl_op` docs.
+fn check_operand_types(fn_item: &syn::ItemFn) -> syn::Result<()> {
+    for input in &fn_item.sig.inputs {
+        let mut ty = match input {
+            syn::FnArg::Receiver(receiver) => receiver.ty.as_ref(),
+            syn::FnArg::Typed(pat) => pat.ty.as_ref()
+        };
+
+        while let syn::Type::Reference(syn::TypeReference { elem, .. }) | syn::Type::Paren(syn::TypeParen { elem, .. }) = ty {
+            ty = elem;
+        }
+
+        if let syn::Type::Macro(ty) = ty {
+            return Err(syn::Error::new_spanned(ty, "Operand types must be concrete types, not macro invocations."));
+        }
+    }
+
+    Ok(())
 }
@@ -0,0 +1,58 @@
+/// The operator traits of `std::ops` and the names of their required methods.
+const OPERATORS: &[(&str, &str)] = &[
+    ("Add", "add"),
+    ("Sub", "sub"),
+    ("Mul", "mul"),
+    ("Div", "div"),
+    ("Rem", "rem"),
+    ("BitAnd", "bitand"),
+    ("BitOr", "bitor"),
+    ("BitXor", "bitxor"),
+    ("Shl", "shl"),
+    ("Shr", "shr"),
+    ("Neg", "neg"),
+    ("Not", "not"),
+    ("AddAssign", "add_assign"),
+    ("SubAssign", "sub_assign"),
+    ("MulAssign", "mul_assign"),
+    ("DivAssign", "div_assign"),
+    ("RemAssign", "rem_assign"),
+    ("BitAndAssign", "bitand_assign"),
+    ("BitOrAssign", "bitor_assign"),
+    ("BitXorAssign", "bitxor_assign"),
+    ("ShlAssign", "shl_assign"),
+    ("ShrAssign", "shr_assign"),
+];
+
+/// Looks up the method name of a known operator trait by the last segment of its path.
+pub(crate) fn method_name(trait_path: &syn::TypePath) -> Option<syn::Ident> {
+    let segment = trait_path.path.segments.last()?;
+    let name = segment.ident.to_string();
+
+    OPERATORS.iter()
+        .find(|(op, _)| *op == name)
+        .map(|(_, method)| syn::Ident::new(method, segment.ident.span()))
+}
+
+/// Looks up the known operator trait whose method has the given name.
+pub(crate) fn operator_name(method: &syn::Ident) -> Option<&'static str> {
+    OPERATORS.iter()
+        .find(|(_, name)| method == name)
+        .map(|(op, _)| *op)
+}
+
+/// The comparison traits of `std::cmp` and the names of their required methods.
+const COMPARISONS: &[(&str, &str)] = &[
+    ("PartialEq", "eq"),
+    ("PartialOrd", "partial_cmp"),
+];
+
+/// Looks up the method name of a known comparison trait by the last segment of its path.
+pub(crate) fn comparison_name(trait_path: &syn::TypePath) -> Option<syn::Ident> {
+    let segment = trait_path.path.segments.last()?;
+    let name = segment.ident.to_string();
+
+    COMPARISONS.iter()
+        .find(|(op, _)| *op == name)
+        .map(|(_, method)| syn::Ident::new(method, segment.ident.span()))
+}
@@ -0,0 +1,1385 @@
+use quote::ToTokens;
+
+/// Splits the expansion of a wrapper macro into the functions it forwards to the inner macros.
+fn expanded_fns(tokens: proc_macro2::TokenStream) -> Vec<syn::ItemFn> {
+    let file: syn::File = syn::parse2(tokens).unwrap();
+
+    file.items.into_iter()
+        .map(|item| match item {
+            syn::Item::Fn(item) => item,
+            _ => panic!("Expected only functions in the expansion.")
+        })
+        .collect()
+}
+
+fn has_attr(item: &syn::ItemFn, attr: &str) -> bool {
+    item.attrs.iter().any(|a| a.meta.to_token_stream().to_string() == attr)
+}
+
+fn has_borrowed_operand(item: &syn::ItemFn) -> bool {
+    item.sig.inputs.iter().any(|arg| {
+        let ty = match arg {
+            syn::FnArg::Receiver(r) => &r.ty,
+            syn::FnArg::Typed(t) => &t.ty
+        };
+
+        matches!(ty.as_ref(), syn::Type::Reference(_))
+    })
+}
+
+#[test]
+fn test_inline_refs_only_on_borrowed_permutations() {
+    let fns = expanded_fns(crate::expand_ops(
+        syn::parse_quote!(Add; inline_refs),
+        syn::parse_quote! {
+            fn add(self: Buffer, rhs: Buffer) -> Buffer {
+                Buffer(self.0 + rhs.0)
+            }
+        }
+    ));
+
+    assert_eq!(4, fns.len());
+
+    for item in &fns {
+        assert_eq!(has_borrowed_operand(item), has_attr(item, "inline"));
+    }
+}
+
+#[test]
+fn test_no_inline_without_inline_refs() {
+    let fns = expanded_fns(crate::expand_ops(
+        syn::parse_quote!(Add),
+        syn::parse_quote! {
+            fn add(self: Buffer, rhs: Buffer) -> Buffer {
+                Buffer(self.0 + rhs.0)
+            }
+        }
+    ));
+
+    assert!(fns.iter().all(|item| !has_attr(item, "inline")));
+}
+
+#[test]
+fn test_clone_refs_bounds_only_borrowed_operands() {
+    let fns = expanded_fns(crate::expand_ops(
+        syn::parse_quote!(Add; clone_refs),
+        syn::parse_quote! {
+            fn add<T>(self: Wrapper<T>, rhs: Other<T>) -> Wrapper<T> {
+                Wrapper(self.0 + rhs.0)
+            }
+        }
+    ));
+
+    let bounds: Vec<String> = fns.iter()
+        .map(|item| item.sig.generics.where_clause.to_token_stream().to_string())
+        .collect();
+
+    assert_eq!("", bounds[0]);
+    assert_eq!("where Wrapper < T > : Clone , Other < T > : Clone", bounds[1]);
+    assert_eq!("where Other < T > : Clone", bounds[2]);
+    assert_eq!("where Wrapper < T > : Clone", bounds[3]);
+}
+
+fn docs(item: &syn::ItemFn) -> Vec<String> {
+    item.attrs.iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .map(|attr| match &attr.meta {
+            syn::Meta::NameValue(syn::MetaNameValue {
+                value: syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(doc), .. }), ..
+            }) => doc.value(),
+            _ => panic!("Expected a doc string.")
+        })
+        .collect()
+}
+
+#[test]
+fn test_doc_all_runs_examples_once() {
+    let fns = expanded_fns(crate::expand_ops(
+        syn::parse_quote!(Add; doc_all),
+        syn::parse_quote! {
+            /// Adds two numbers.
+            ///
+            /// ```
+            /// assert_eq!(2, Num(1) + Num(1));
+            /// ```
+            ///
+            /// ```rust,no_run
+            /// let _ = &Num(1) + &Num(1);
+            /// ```
+            fn add(self: Num, rhs: Num) -> Num {
+                Num(self.0 + rhs.0)
+            }
+        }
+    ));
+
+    assert_eq!(
+        vec![" Adds two numbers.", "", " ```", " assert_eq!(2, Num(1) + Num(1));", " ```",
+             "", " ```rust,no_run", " let _ = &Num(1) + &Num(1);", " ```"],
+        docs(&fns[0])
+    );
+
+    for item in &fns[1..] {
+        assert_eq!(
+            vec![" Adds two numbers.", "", " ```ignore", " assert_eq!(2, Num(1) + Num(1));", " ```",
+                 "", " ```rust,no_run,ignore", " let _ = &Num(1) + &Num(1);", " ```"],
+            docs(item)
+        );
+    }
+}
+
+#[test]
+fn test_docs_only_on_owned_permutation_by_default() {
+    let fns = expanded_fns(crate::expand_ops(
+        syn::parse_quote!(Add),
+        syn::parse_quote! {
+            /// Adds two numbers.
+            fn add(self: Num, rhs: Num) -> Num {
+                Num(self.0 + rhs.0)
+            }
+        }
+    ));
+
+    assert_eq!(vec![" Adds two numbers."], docs(&fns[0]));
+    assert!(fns[1..].iter().all(|item| docs(item).is_empty()));
+}
+
+#[test]
+fn test_free_generic_rhs_skips_borrowed_rhs() {
+    let fns = expanded_fns(crate::expand_ops(
+        syn::parse_quote!(Add),
+        syn::parse_quote! {
+            fn add<Rhs: Into<Matrix>>(self: Matrix, rhs: Rhs) -> Matrix {
+                Matrix(self.0 + rhs.into().0)
+            }
+        }
+    ));
+
+    let signatures: Vec<String> = fns.iter()
+        .map(|item| item.sig.inputs.to_token_stream().to_string())
+        .collect();
+
+    assert_eq!(vec!["self : Matrix , rhs : Rhs", "self : & Matrix , rhs : Rhs"], signatures);
+}
+
+#[test]
+fn test_deref_operands_forwards_to_borrowed_permutation() {
+    let fns = expanded_fns(crate::expand_ops(
+        syn::parse_quote!(Add; deref_operands),
+        syn::parse_quote! {
+            fn add(self: Value, rhs: Value) -> Value {
+                combine(self, rhs)
+            }
+        }
+    ));
+
+    let bodies: Vec<String> = fns.iter()
+        .map(|item| item.block.to_token_stream().to_string())
+        .collect();
+
+    assert_eq!("{ < & Value as Add < & Value > > :: add (& self , & rhs) }", bodies[0]);
+    assert_eq!("{ combine (self , rhs) }", bodies[1]);
+    assert_eq!("{ < & Value as Add < & Value > > :: add (& self , rhs) }", bodies[2]);
+    assert_eq!("{ < & Value as Add < & Value > > :: add (self , & rhs) }", bodies[3]);
+}
+
+#[test]
+fn test_const_expr_output_is_preserved() {
+    let fns = expanded_fns(crate::expand_ops(
+        syn::parse_quote!(Add),
+        syn::parse_quote! {
+            fn add<const N: usize>(self: Stack<N>, rhs: Stack<1>) -> Stack<{N + 1}> where [(); N + 1]: {
+                self.push(rhs)
+            }
+        }
+    ));
+
+    for item in &fns {
+        assert_eq!("-> Stack < { N + 1 } >", item.sig.output.to_token_stream().to_string());
+    }
+}
+
+#[test]
+fn test_newtype_projects_borrowed_fields() {
+    let fns = expanded_fns(crate::expand_ops(
+        syn::parse_quote!(Add; newtype = 0),
+        syn::parse_quote! {
+            fn add(self: Name, rhs: Name) -> Name {
+                Name(self.0 + &rhs.0)
+            }
+        }
+    ));
+
+    let bodies: Vec<String> = fns.iter()
+        .map(|item| item.block.to_token_stream().to_string())
+        .collect();
+
+    assert_eq!("{ Name (self . 0 + & rhs . 0) }", bodies[0]);
+    assert_eq!("{ Self :: Output { 0 : Add :: add (& self . 0 , & rhs . 0) } }", bodies[1]);
+    assert_eq!("{ Self :: Output { 0 : Add :: add (self . 0 , & rhs . 0) } }", bodies[2]);
+    assert_eq!("{ Self :: Output { 0 : Add :: add (& self . 0 , rhs . 0) } }", bodies[3]);
+}
+
+#[test]
+fn test_output_mixing_operand_generics_is_preserved() {
+    let tokens = crate::expand_ops(
+        syn::parse_quote!(Mul),
+        syn::parse_quote! {
+            fn mul<const M: usize, const N: usize, const P: usize>(self: Matrix<M, N>, rhs: Matrix<N, P>) -> Matrix<M, P> {
+                self.product(rhs)
+            }
+        }
+    ).to_string();
+
+    assert!(tokens.starts_with("# [opimps :: impl_op (Mul)] fn mul < const M : usize , const N : usize , const P : usize > (self : Matrix < M , N > , rhs : Matrix < N , P >) -> Matrix < M , P >"));
+}
+
+fn impl_header(tokens: proc_macro2::TokenStream) -> String {
+    let item: syn::ItemImpl = syn::parse2(tokens).unwrap();
+    let (_, path, _) = item.trait_.unwrap();
+
+    format!("{} for {}", path.to_token_stream(), item.self_ty.to_token_stream())
+}
+
+#[test]
+fn test_rhs_form() {
+    let op = |args: proc_macro2::TokenStream| {
+        impl_header(crate::expand_op(syn::parse2(args).unwrap(), syn::parse_quote! {
+            fn add(self: Value, rhs: Value) -> Value {
+                combine(self, rhs)
+            }
+        }))
+    };
+
+    assert_eq!("Add < Value > for Value", op(quote::quote!(Add)));
+    assert_eq!("Add < Value > for Value", op(quote::quote!(Add; rhs_form = concrete)));
+    assert_eq!("Add for Value", op(quote::quote!(Add; rhs_form = omitted)));
+    assert_eq!("Add < Self > for Value", op(quote::quote!(Add; rhs_form = self)));
+}
+
+#[test]
+fn test_rhs_form_only_applies_to_identical_types() {
+    let header = impl_header(crate::expand_op(
+        syn::parse_quote!(Add; rhs_form = self),
+        syn::parse_quote! {
+            fn add(self: Value, rhs: &Value) -> Value {
+                combine(self, rhs)
+            }
+        }
+    ));
+
+    assert_eq!("Add < & Value > for Value", header);
+}
+
+#[test]
+fn test_rhs_form_is_forwarded_to_every_permutation() {
+    let fns = expanded_fns(crate::expand_ops(
+        syn::parse_quote!(Add; rhs_form = omitted),
+        syn::parse_quote! {
+            fn add(self: Value, rhs: Value) -> Value {
+                combine(self, rhs)
+            }
+        }
+    ));
+
+    for item in &fns {
+        let attr = item.attrs.iter().find(|attr| attr.path().segments.last().unwrap().ident == "impl_op").unwrap();
+        assert_eq!("Add ; rhs_form = omitted", attr.meta.require_list().unwrap().tokens.to_string());
+    }
+}
+
+#[test]
+fn test_generic_rhs_bound_names_self_type() {
+    let fns = expanded_fns(crate::expand_ops(
+        syn::parse_quote!(Add),
+        syn::parse_quote! {
+            fn add<R: Into<Self>>(self: Meters, rhs: R) -> Meters where R: Copy + PartialOrd<Self> {
+                Meters(self.0 + rhs.into().0)
+            }
+        }
+    ));
+
+    assert_eq!(2, fns.len());
+
+    for item in &fns {
+        assert_eq!("< R : Into < Meters > >", item.sig.generics.to_token_stream().to_string());
+        assert_eq!("where R : Copy + PartialOrd < Meters >", item.sig.generics.where_clause.to_token_stream().to_string());
+    }
+}
+
+#[test]
+fn test_clone_refs_leaves_concrete_operands_unbounded() {
+    let fns = expanded_fns(crate::expand_ops(
+        syn::parse_quote!(Add; clone_refs),
+        syn::parse_quote! {
+            fn add(self: Big, rhs: Big) -> Big {
+                Big(self.0.into_iter().chain(rhs.0).collect())
+            }
+        }
+    ));
+
+    assert!(fns.iter().all(|item| item.sig.generics.where_clause.is_none()));
+    assert_eq!(
+        "{ < Big as Add < Big > > :: add (< Big as :: core :: clone :: Clone > :: clone (self) , < Big as :: core :: clone :: Clone > :: clone (rhs)) }",
+        fns[1].block.to_token_stream().to_string()
+    );
+}
+
+#[test]
+fn test_push_field_generates_body() {
+    let fns = expanded_fns(crate::expand_ops(
+        syn::parse_quote!(Add; push_field = 0),
+        syn::parse_quote! {
+            fn add(self: Query, rhs: Clause) -> Query {}
+        }
+    ));
+
+    assert_eq!("{ let mut lhs = self ; lhs . 0 . push (rhs) ; lhs }", fns[0].block.to_token_stream().to_string());
+    assert_eq!("{ < Query as Add < Clause > > :: add (< Query as :: core :: clone :: Clone > :: clone (self) , < Clause as :: core :: clone :: Clone > :: clone (rhs)) }", fns[1].block.to_token_stream().to_string());
+}
+
+#[test]
+fn test_macro_operand_types_are_rejected() {
+    let error = crate::check_operand_types(&syn::parse_quote! {
+        fn add(self: &make_type!(), rhs: i32) -> i32 {
+            self.0 + rhs
+        }
+    }).unwrap_err();
+
+    assert_eq!("Operand types must be concrete types, not macro invocations.", error.to_string());
+    assert!(crate::check_operand_types(&syn::parse_quote! {
+        fn add(self: &Value, rhs: i32) -> i32 {
+            self.0 + rhs
+        }
+    }).is_ok());
+}
+
+#[test]
+fn test_no_output_omits_associated_type() {
+    let item: syn::ItemImpl = syn::parse2(crate::expand_op(
+        syn::parse_quote!(Overlaps; no_output),
+        syn::parse_quote! {
+            fn overlaps(self: Span, rhs: Span) -> bool {
+                self.start < rhs.end && rhs.start < self.end
+            }
+        }
+    )).unwrap();
+
+    assert_eq!(1, item.items.len());
+
+    let syn::ImplItem::Fn(method) = &item.items[0] else {
+        panic!("Expected only the method in the implementation.");
+    };
+
+    assert_eq!("-> bool", method.sig.output.to_token_stream().to_string());
+}
+
+#[test]
+fn test_borrowing_signature_requires_references() {
+    let sig = |item: syn::ItemFn| crate::borrowing_signature(&item.sig).err().map(|e| e.to_string());
+
+    assert_eq!(None, sig(syn::parse_quote! { fn deref(self: &Name) -> &String { &self.0 } }));
+    assert_eq!(
+        Some("Expected a reference, e.g. `&T` or `&mut T`.".to_string()),
+        sig(syn::parse_quote! { fn deref(self: &Name) -> String { self.0.clone() } })
+    );
+    assert_eq!(
+        Some("The returned reference must be as mutable as `self`.".to_string()),
+        sig(syn::parse_quote! { fn deref_mut(self: &mut Name) -> &String { &self.0 } })
+    );
+}
+
+#[test]
+fn test_body_is_emitted_once() {
+    let tokens = crate::expand_ops(
+        syn::parse_quote!(Add),
+        syn::parse_quote! {
+            fn add(self: Value, rhs: Value) -> Value {
+                combine(self, rhs)
+            }
+        }
+    );
+
+    assert_eq!(1, tokens.to_string().matches("combine").count());
+
+    let fns = expanded_fns(tokens);
+    assert_eq!("{ < & Value as Add < & Value > > :: add (& self , & rhs) }", fns[0].block.to_token_stream().to_string());
+    assert_eq!("{ combine (self , rhs) }", fns[1].block.to_token_stream().to_string());
+}
+
+#[test]
+fn test_body_is_repeated_for_destructured_rhs() {
+    let tokens = crate::expand_ops(
+        syn::parse_quote!(Add),
+        syn::parse_quote! {
+            fn add(self: Value, (x, y): (i32, i32)) -> Value {
+                combine(self, x, y)
+            }
+        }
+    );
+
+    assert_eq!(4, tokens.to_string().matches("combine").count());
+}
+
+#[test]
+fn test_missing_rhs_is_reported_on_signature() {
+    let tokens = crate::expand_ops(
+        syn::parse_quote!(Add),
+        syn::parse_quote! {
+            fn add(self: Value) -> Value {
+                self
+            }
+        }
+    );
+
+    assert_eq!(":: core :: compile_error ! { \"Requires two arguments (self: T1, rhs: T2).\" }", tokens.to_string());
+}
+
+#[test]
+fn test_op_ref_requires_two_arguments() {
+    let tokens = crate::expand_op_ref(
+        syn::parse_quote!(Row),
+        syn::parse_quote! {
+            fn row(self: &Matrix) -> &[f64] {
+                &self.data
+            }
+        }
+    );
+
+    assert_eq!(":: core :: compile_error ! { \"Requires two arguments (self: T1, rhs: T2).\" }", tokens.to_string());
+}
+
+#[test]
+fn test_non_receiver_is_reported_on_parameter() {
+    let error = crate::binary_params(&syn::parse_quote! {
+        fn mul(lhs: Value, rhs: Value) -> Value
+    }).err().unwrap();
+
+    assert_eq!("The first parameter must be a receiver like `self: T`.", error.to_string());
+
+    let error = crate::unary_param(&syn::parse_quote! {
+        fn neg(value: Value) -> Value
+    }).err().unwrap();
+
+    assert_eq!("The first parameter must be a receiver like `self: T`.", error.to_string());
+}
+
+#[test]
+fn test_return_types_are_checked() {
+    let error = crate::output_type(&syn::parse_quote! {
+        fn add(self: Value, rhs: Value)
+    }).err().unwrap();
+
+    assert_eq!("The operator method needs an explicit return type like `-> T`.", error.to_string());
+
+    let error = crate::check_assign_output(&syn::parse_quote! {
+        fn add_assign(self: Value, rhs: Value) -> Value
+    }).unwrap_err();
+
+    assert_eq!("Assignment operators can't have a return type.", error.to_string());
+    assert!(crate::check_assign_output(&syn::parse_quote! {
+        fn add_assign(self: Value, rhs: Value)
+    }).is_ok());
+}
+
+#[test]
+fn test_omitted_output_defaults_to_concrete_self() {
+    let output = crate::binary_output_type(&syn::parse_quote! {
+        fn add(self: &Meters, rhs: Meters)
+    }).ok().unwrap();
+
+    assert_eq!("Meters", output.to_token_stream().to_string());
+
+    let error = crate::binary_output_type(&syn::parse_quote! {
+        fn add<T>(self: Wrapper<T>, rhs: Wrapper<T>)
+    }).err().unwrap();
+
+    assert_eq!("The return type can only be omitted when the type of `self` isn't generic; write `-> T`.", error.to_string());
+}
+
+#[test]
+fn test_untyped_receiver_is_rejected() {
+    let error = crate::binary_params(&syn::parse_quote! {
+        fn push(&mut self, rhs: Item) -> usize
+    }).err().unwrap();
+
+    assert_eq!("The receiver must be typed, e.g. `self: &mut T` rather than `&mut self`.", error.to_string());
+}
+
+#[test]
+fn test_declared_reference_rhs_is_not_borrowed_again() {
+    let fns = expanded_fns(crate::expand_ops(
+        syn::parse_quote!(Add),
+        syn::parse_quote! {
+            fn add(self: Names, rhs: &str) -> Names {
+                self.with(rhs)
+            }
+        }
+    ));
+
+    let signatures: Vec<String> = fns.iter()
+        .map(|item| item.sig.inputs.to_token_stream().to_string())
+        .collect();
+
+    assert_eq!(vec!["self : & Names , rhs : & str", "self : Names , rhs : & str"], signatures);
+}
+
+#[test]
+fn test_where_clause_is_kept_on_every_permutation() {
+    let fns = expanded_fns(crate::expand_ops(
+        syn::parse_quote!(Add; clone_refs),
+        syn::parse_quote! {
+            fn add<T>(self: Poly<T>, rhs: Poly<T>) -> Poly<T> where for<'a> &'a T: Add<&'a T, Output = T> {
+                Poly(self.0.iter().zip(rhs.0.iter()).map(|(a, b)| a + b).collect())
+            }
+        }
+    ));
+
+    assert_eq!(4, fns.len());
+
+    for item in fns {
+        let where_clause = item.sig.generics.where_clause.to_token_stream().to_string();
+        assert!(where_clause.starts_with("where for < 'a > & 'a T : Add < & 'a T , Output = T >"), "{}", where_clause);
+    }
+}
+
+#[test]
+fn test_only_generates_listed_permutations() {
+    let fns = expanded_fns(crate::expand_ops(
+        syn::parse_quote!(Add, only(ref_ref, owned_owned)),
+        syn::parse_quote! {
+            fn add(self: Value, rhs: Value) -> Value {
+                combine(self, rhs)
+            }
+        }
+    ));
+
+    let signatures: Vec<String> = fns.iter()
+        .map(|item| item.sig.inputs.to_token_stream().to_string())
+        .collect();
+
+    assert_eq!(vec!["self : Value , rhs : Value", "self : & Value , rhs : & Value"], signatures);
+}
+
+#[test]
+fn test_only_keeps_the_forwarding_target() {
+    let tokens = crate::expand_ops(
+        syn::parse_quote!(Add; deref_operands, only(owned_owned)),
+        syn::parse_quote! {
+            fn add(self: Value, rhs: Value) -> Value {
+                combine(self, rhs)
+            }
+        }
+    );
+
+    assert!(tokens.to_string().contains("`deref_operands` forwards to `ref_ref`, which must be generated."));
+
+    // Without `deref_operands`, the body is just emitted in every listed permutation.
+    let fns = expanded_fns(crate::expand_ops(
+        syn::parse_quote!(Add; only(owned_owned, owned_ref)),
+        syn::parse_quote! {
+            fn add(self: Value, rhs: Value) -> Value {
+                combine(self, rhs)
+            }
+        }
+    ));
+
+    for item in fns {
+        assert_eq!("{ combine (self , rhs) }", item.block.to_token_stream().to_string());
+    }
+}
+
+#[test]
+fn test_skip_excludes_permutations() {
+    let fns = expanded_fns(crate::expand_ops(
+        syn::parse_quote!(Mul; skip(owned_owned)),
+        syn::parse_quote! {
+            fn mul<T: Copy>(self: Value<T>, rhs: Value<T>) -> Value<T> where T: Mul<Output = T> {
+                combine(self, rhs)
+            }
+        }
+    ));
+
+    assert_eq!(3, fns.len());
+    assert!(fns.iter().all(has_borrowed_operand));
+    assert!(fns.iter().all(|item| item.sig.generics.where_clause.is_some()));
+
+    let tokens = crate::expand_ops(
+        syn::parse_quote!(Mul; skip(owned_owned, ref_owned, owned_ref, ref_ref)),
+        syn::parse_quote! {
+            fn mul(self: Value, rhs: Value) -> Value {
+                combine(self, rhs)
+            }
+        }
+    );
+
+    assert!(tokens.to_string().contains("None of the permutations are left to generate."));
+}
+
+#[test]
+fn test_commutative_mirrors_the_selected_permutations() {
+    let fns = expanded_fns(crate::expand_ops(
+        syn::parse_quote!(Mul; commutative, skip(owned_owned, ref_owned)),
+        syn::parse_quote! {
+            fn mul(self: Speed, rhs: Hours) -> Distance {
+                Distance(self.0 * rhs.0)
+            }
+        }
+    ));
+
+    let swapped = fns.last().unwrap();
+    assert!(has_attr(swapped, "opimps :: impl_ops (Mul ; skip (owned_owned , owned_ref))"));
+
+    let fns = expanded_fns(crate::expand_ops(
+        syn::parse_quote!(Mul; commutative, no_output, only(owned_ref)),
+        syn::parse_quote! {
+            fn mul(self: Speed, rhs: Hours) -> Distance {
+                Distance(self.0 * rhs.0)
+            }
+        }
+    ));
+
+    let swapped = fns.last().unwrap();
+    assert!(has_attr(swapped, "opimps :: impl_ops (Mul ; no_output , only (ref_owned))"));
+}
+
+#[test]
+fn test_commutative_rejects_same_types() {
+    let tokens = crate::expand_ops(
+        syn::parse_quote!(Add; commutative),
+        syn::parse_quote! {
+            fn add(self: Meters, rhs: Meters) -> Meters {
+                Meters(self.0 + rhs.0)
+            }
+        }
+    );
+
+    assert!(tokens.to_string().contains("`commutative` requires the operands to have different types."));
+}
+
+#[test]
+fn test_primitive_rhs_type_list() {
+    let args: crate::OpArgs = syn::parse_quote!(Shl; u8, u16; strict);
+    assert_eq!(2, args.rhs_types.len());
+    assert!(args.strict);
+
+    let args: crate::OpArgs = syn::parse_quote!(Shl; strict);
+    assert!(args.rhs_types.is_empty());
+    assert!(args.strict);
+
+    let error = crate::check_distinct_types(&[syn::parse_quote!(u8), syn::parse_quote!(u16), syn::parse_quote!(u8)]).unwrap_err();
+    assert_eq!("This type is already listed.", error.to_string());
+}
+
+#[test]
+fn test_rhs_types_option() {
+    let args: crate::OpArgs = syn::parse_quote!(Mul, rhs_types(f32, Scale<f64>), strict);
+    assert_eq!(2, args.rhs_types.len());
+    assert!(args.strict);
+
+    let error = syn::parse2::<crate::OpArgs>(quote::quote!(Shl; u8; rhs_types(u16))).err().unwrap();
+    assert_eq!("The `rhs` types are already listed.", error.to_string());
+}
+
+#[test]
+fn test_more_traits_are_parsed_before_options() {
+    let args: crate::OpArgs = syn::parse_quote!(Add, std::ops::Sub, strict);
+    assert_eq!(1, args.more_traits.len());
+    assert!(args.strict);
+
+    let args: crate::OpArgs = syn::parse_quote!(Add, rhs_types(u8, u16));
+    assert!(args.more_traits.is_empty());
+    assert_eq!(2, args.rhs_types.len());
+
+    let error = crate::check_single_trait(&syn::parse_quote!(Neg, Not)).err().unwrap();
+    assert_eq!("Only one trait can be implemented by this macro.", error.to_string());
+}
+
+#[test]
+fn test_iterator_bound_is_required() {
+    let tokens = crate::expand_iter_fold(
+        syn::parse_quote!(),
+        syn::parse_quote! {
+            fn sum<I: IntoIterator<Item = Total>>(iter: I) -> Total {
+                Total(iter.into_iter().map(|t| t.0).sum())
+            }
+        },
+        quote::quote! { ::core::iter::Sum },
+        "sum"
+    );
+
+    assert_eq!(":: core :: compile_error ! { \"Requires an iterator type parameter like `I: Iterator<Item = T>`.\" }", tokens.to_string());
+
+    let tokens = crate::expand_iter_fold(
+        syn::parse_quote!(),
+        syn::parse_quote! {
+            fn sum<I>(iter: I) -> Total where I: Iterator<Item = Total> + Clone {
+                Total(iter.map(|t| t.0).sum())
+            }
+        },
+        quote::quote! { ::core::iter::Sum },
+        "sum"
+    );
+
+    assert_eq!(":: core :: compile_error ! { \"The iterator can only be bounded by `Iterator<Item = T>`.\" }", tokens.to_string());
+}
+
+#[test]
+fn test_concrete_items_are_borrowed_only_when_asked() {
+    let fold = |args| syn::parse2::<syn::File>(crate::expand_iter_fold(
+        args,
+        syn::parse_quote! {
+            fn sum<I: Iterator<Item = Tot>>(iter: I) -> Tot {
+                Tot(iter.map(|t| t.0).sum())
+            }
+        },
+        quote::quote! { ::core::iter::Sum },
+        "sum"
+    )).unwrap();
+
+    let file = fold(syn::parse_quote!());
+    assert_eq!(1, file.items.len());
+    assert!(!file.to_token_stream().to_string().contains("Clone"));
+
+    let file = fold(syn::parse_quote!(borrowed));
+    assert_eq!(2, file.items.len());
+}
+
+#[test]
+fn test_from_requires_a_single_value() {
+    let tokens = crate::expand_conversion(syn::parse_quote! {
+        fn from(self: Feet) -> Meters {
+            Meters(self.0 * 0.3)
+        }
+    }, false);
+
+    assert_eq!(":: core :: compile_error ! { \"Requires a single argument (value: T).\" }", tokens.to_string());
+}
+
+#[test]
+fn test_try_from_requires_a_result() {
+    let tokens = crate::expand_conversion(syn::parse_quote! {
+        fn try_from(value: u8) -> io::Result<Percent> {
+            Ok(Percent(value))
+        }
+    }, true);
+
+    assert_eq!(":: core :: compile_error ! { \"The return type must be a `Result<T, E>`.\" }", tokens.to_string());
+}
+
+#[test]
+fn test_impls_are_automatically_derived() {
+    let file: syn::File = syn::parse2(crate::expand_op(
+        syn::parse_quote!(Add),
+        syn::parse_quote! {
+            #[inline]
+            fn add(self: Value, rhs: Value) -> Value {
+                Value(self.0 + rhs.0)
+            }
+        }
+    )).unwrap();
+
+    let [syn::Item::Impl(item)] = file.items.as_slice() else {
+        panic!("Expected a single implementation.");
+    };
+
+    assert_eq!(1, item.attrs.len());
+    assert!(item.attrs[0].path().is_ident("automatically_derived"));
+
+    let [syn::ImplItem::Type(_), syn::ImplItem::Fn(method)] = item.items.as_slice() else {
+        panic!("Expected the output and the method.");
+    };
+
+    assert!(method.attrs[0].path().is_ident("inline"));
+}
+
+#[test]
+fn test_cmp_ops_cfg_gates_every_impl() {
+    let file: syn::File = syn::parse2(crate::expand_cmp_ops(
+        syn::parse_quote!(PartialEq),
+        syn::parse_quote! {
+            #[cfg(any())]
+            #[inline]
+            fn eq(self: Grams, rhs: Ounces) -> bool {
+                self.0 * 10 == rhs.0 * 283
+            }
+        }
+    )).unwrap();
+
+    assert_eq!(3, file.items.len());
+
+    for item in &file.items {
+        let syn::Item::Impl(item) = item else {
+            panic!("Expected only implementations.");
+        };
+
+        assert!(item.attrs.iter().any(|attr| attr.meta.to_token_stream().to_string() == "cfg (any ())"));
+
+        let [syn::ImplItem::Fn(method)] = item.items.as_slice() else {
+            panic!("Expected a single method.");
+        };
+
+        assert!(method.attrs.iter().all(|attr| !attr.path().is_ident("cfg")));
+    }
+}
+
+#[test]
+fn test_conversions_are_automatically_derived() {
+    let file: syn::File = syn::parse2(crate::expand_conversion(syn::parse_quote! {
+        fn from(value: Feet) -> Meters {
+            Meters(value.0 * 0.3048)
+        }
+    }, false)).unwrap();
+
+    assert!(!file.items.is_empty());
+
+    for item in &file.items {
+        let syn::Item::Impl(item) = item else {
+            panic!("Expected only implementations.");
+        };
+
+        assert!(item.attrs.iter().any(|attr| attr.path().is_ident("automatically_derived")));
+    }
+}
+
+#[test]
+fn test_cfg_attr_stays_on_the_method() {
+    let file: syn::File = syn::parse2(crate::expand_op(
+        syn::parse_quote!(Add),
+        syn::parse_quote! {
+            #[cfg(feature = "ops")]
+            #[cfg_attr(feature = "x", inline)]
+            fn add(self: Value, rhs: Value) -> Value {
+                Value(self.0 + rhs.0)
+            }
+        }
+    )).unwrap();
+
+    let [syn::Item::Impl(item)] = file.items.as_slice() else {
+        panic!("Expected a single implementation.");
+    };
+
+    assert!(item.attrs[0].path().is_ident("cfg"));
+    assert!(item.attrs.iter().all(|attr| !attr.path().is_ident("cfg_attr")));
+
+    let [syn::ImplItem::Type(_), syn::ImplItem::Fn(method)] = item.items.as_slice() else {
+        panic!("Expected the output and the method.");
+    };
+
+    assert_eq!(1, method.attrs.len());
+    assert_eq!("cfg_attr (feature = \"x\" , inline)", method.attrs[0].meta.to_token_stream().to_string());
+}
+
+#[test]
+fn test_cmp_ops_generic_rhs_skips_borrowed_self() {
+    let file: syn::File = syn::parse2(crate::expand_cmp_ops(
+        syn::parse_quote!(PartialEq),
+        syn::parse_quote! {
+            fn eq<T: PartialEq>(self: G<T>, rhs: T) -> bool {
+                self.0 == *rhs
+            }
+        }
+    )).unwrap();
+
+    let headers: Vec<String> = file.items.iter()
+        .map(|item| match item {
+            syn::Item::Impl(item) => item.self_ty.to_token_stream().to_string(),
+            _ => panic!("Expected only implementations.")
+        })
+        .collect();
+
+    assert_eq!(vec!["G < T >", "G < T >"], headers);
+}
+
+#[test]
+fn test_generics_must_be_used_by_operands() {
+    let sig: syn::Signature = syn::parse_quote! {
+        fn add<T>(self: Wrapper, rhs: Wrapper) -> Wrapper where Wrapper: Inner<T>
+    };
+
+    let error = crate::check_constrained_generics(&sig, &syn::parse_quote!(Add)).err().unwrap();
+    assert!(error.to_string().starts_with("`T` isn't used by the operand types"));
+
+    let sig: syn::Signature = syn::parse_quote! {
+        fn add<'a, T, const N: usize>(self: &'a Wrapper<T>, rhs: [T; N]) -> Wrapper<T>
+    };
+
+    assert!(crate::check_constrained_generics(&sig, &syn::parse_quote!(Add)).is_ok());
+
+    let sig: syn::Signature = syn::parse_quote! {
+        fn apply<const N: usize>(self: Wrapper, rhs: Wrapper) -> Wrapper
+    };
+
+    assert!(crate::check_constrained_generics(&sig, &syn::parse_quote!(Apply<N>)).is_ok());
+}
+
+#[test]
+fn test_method_of_another_operator_is_rejected() {
+    let error = crate::check_method_name(&syn::parse_quote!(Mul), &syn::parse_quote!(div)).err().unwrap();
+    assert_eq!("`div` is the method of `Div`, but the implemented trait is `Mul`, whose method is `mul`.", error.to_string());
+
+    assert!(crate::check_method_name(&syn::parse_quote!(Mul), &syn::parse_quote!(scale)).is_ok());
+    assert!(crate::check_method_name(&syn::parse_quote!(Sub), &syn::parse_quote!(sub_assign)).is_ok());
+    assert!(crate::check_method_name(&syn::parse_quote!(Scale), &syn::parse_quote!(div)).is_ok());
+}
+
+#[test]
+fn test_ops_block_methods_must_borrow() {
+    let tokens = crate::expand_ops_block(syn::parse_quote! {
+        impl PartialEq<Self> for Version {
+            fn eq(&self, rhs: &Self) -> bool {
+                self.0 == rhs.0
+            }
+
+            fn ne(self, rhs: Self) -> bool {
+                self.0 != rhs.0
+            }
+        }
+    });
+
+    assert_eq!(":: core :: compile_error ! { \"Each method must take `&self` and a borrowed `rhs`, e.g. `fn lt(&self, rhs: &T) -> bool`.\" }", tokens.to_string());
+}
+
+#[test]
+fn test_ops_block_cfgs_gate_the_forwarding_impls() {
+    let file: syn::File = syn::parse2(crate::expand_ops_block(syn::parse_quote! {
+        #[cfg(feature = "cmp")]
+        impl PartialEq for Version {
+            fn eq(&self, rhs: &Self) -> bool {
+                self.0 == rhs.0
+            }
+
+            #[cfg(any())]
+            #[inline]
+            fn ne(&self, rhs: &Self) -> bool {
+                self.0 != rhs.0
+            }
+        }
+    })).unwrap();
+
+    assert_eq!(3, file.items.len());
+
+    for item in &file.items {
+        let syn::Item::Impl(item) = item else {
+            panic!("Expected only implementations.");
+        };
+
+        assert!(item.attrs.iter().any(|attr| attr.meta.to_token_stream().to_string() == "cfg (feature = \"cmp\")"));
+
+        let [syn::ImplItem::Fn(eq), syn::ImplItem::Fn(ne)] = item.items.as_slice() else {
+            panic!("Expected both methods.");
+        };
+
+        assert!(eq.attrs.is_empty());
+        assert_eq!("cfg (any ())", ne.attrs[0].meta.to_token_stream().to_string());
+    }
+}
+
+#[test]
+fn test_bare_generic_rhs_is_cloned_when_borrowed() {
+    let fns = expanded_fns(crate::expand_ops(
+        syn::parse_quote!(Add),
+        syn::parse_quote! {
+            fn add<T: Add<Output = T> + Copy>(self: Wrapper<T>, rhs: T) -> Wrapper<T> {
+                Wrapper(self.0 + rhs)
+            }
+        }
+    ));
+
+    let bodies: Vec<_> = fns.iter().map(|item| item.block.to_token_stream().to_string()).collect();
+
+    assert_eq!(4, fns.len());
+    assert_eq!(1, bodies.iter().filter(|body| body.contains("self . 0 + rhs")).count());
+    assert_eq!(2, bodies.iter().filter(|body| body.contains("rhs . clone ()")).count());
+}
+
+#[test]
+fn test_docs_go_on_the_method() {
+    let file: syn::File = syn::parse2(crate::expand_op_assign(
+        syn::parse_quote!(AddAssign),
+        syn::parse_quote! {
+            /// Adds the value in place.
+            #[inline]
+            #[cfg(feature = "std")]
+            fn add_assign(self: Value, rhs: Value) {
+                self.0 += rhs.0;
+            }
+        }
+    )).unwrap();
+
+    let [syn::Item::Impl(item)] = file.items.as_slice() else {
+        panic!("Expected a single implementation.");
+    };
+
+    let impl_attrs: Vec<_> = item.attrs.iter().map(|attr| attr.path().to_token_stream().to_string()).collect();
+    assert_eq!(vec!["cfg", "automatically_derived"], impl_attrs);
+
+    let [syn::ImplItem::Fn(method)] = item.items.as_slice() else {
+        panic!("Expected only the method.");
+    };
+
+    let method_attrs: Vec<_> = method.attrs.iter().map(|attr| attr.path().to_token_stream().to_string()).collect();
+    assert_eq!(vec!["doc", "inline"], method_attrs);
+}
+
+#[test]
+fn test_operators_must_be_placed_on_a_fn() {
+    use syn::parse::Parser;
+
+    let err = crate::parse_operator_fn.parse2(quote::quote! {
+        struct A(i32);
+    }).err().unwrap();
+
+    assert_eq!("opimps attributes must be placed on a free `fn` with a `self: T` first parameter.", err.to_string());
+
+    let err = crate::parse_operator_fn.parse2(quote::quote! {
+        fn add(self: A, rhs: A) -> A { A(self.0 + ) }
+    }).err().unwrap();
+
+    assert_ne!("opimps attributes must be placed on a free `fn` with a `self: T` first parameter.", err.to_string());
+}
+
+#[test]
+fn test_inline_goes_on_every_permutation_method() {
+    let fns = expanded_fns(crate::expand_ops(
+        syn::parse_quote!(Add),
+        syn::parse_quote! {
+            #[inline(always)]
+            fn add(self: Vector, rhs: Vector) -> Vector {
+                Vector(self.0 + rhs.0)
+            }
+        }
+    ));
+
+    assert_eq!(4, fns.len());
+
+    let methods: Vec<String> = fns.into_iter()
+        .map(|mut item| {
+            let position = item.attrs.iter().position(|attr| attr.path().segments.last().unwrap().ident == "impl_op").unwrap();
+            let args = item.attrs.remove(position).parse_args().unwrap();
+            let file: syn::File = syn::parse2(crate::expand_op(args, item)).unwrap();
+
+            let [syn::Item::Impl(item)] = file.items.as_slice() else {
+                panic!("Expected a single implementation.");
+            };
+
+            let Some(syn::ImplItem::Fn(method)) = item.items.last() else {
+                panic!("Expected the method last.");
+            };
+
+            method.attrs.iter().map(|attr| attr.to_token_stream().to_string()).collect::<Vec<_>>().join(" ")
+        })
+        .collect();
+
+    assert_eq!(4, methods.iter().filter(|attrs| attrs.contains("# [inline (always)]")).count());
+}
+
+#[test]
+fn test_output_reference_requires_a_borrowed_receiver() {
+    let err = crate::expand_op(
+        syn::parse_quote!(Pick),
+        syn::parse_quote! {
+            fn pick(self: Words, rhs: usize) -> &String {
+                &self.0[rhs]
+            }
+        }
+    );
+
+    assert!(err.to_string().contains("The output reference needs the lifetime of a borrowed `self`"));
+}
+
+#[test]
+fn test_output_names_the_associated_type() {
+    let tokens = crate::expand_op(
+        syn::parse_quote!(Join; output = Result, out_alias = Out),
+        syn::parse_quote! {
+            fn join(self: Pair, rhs: Pair) -> Pair {
+                Out { x: self.x + rhs.x }
+            }
+        }
+    ).to_string();
+
+    assert!(tokens.contains("type Result = Pair ;"));
+    assert!(tokens.contains("-> Self :: Result"));
+    assert!(tokens.contains("Self :: Result { x :"));
+    assert!(!tokens.contains("Output"));
+}
+
+#[test]
+fn test_out_alias_declared_in_the_body_is_reported() {
+    for body in [
+        quote::quote! { { struct Out; Point(self.0 + rhs.0) } },
+        quote::quote! { { fn Out() {} Point(self.0 + rhs.0) } },
+        quote::quote! { { let Out = self.0 + rhs.0; Point(Out) } }
+    ] {
+        let tokens = crate::expand_op(
+            syn::parse_quote!(Add; out_alias = Out),
+            syn::parse_quote! {
+                fn add(self: Point, rhs: Point) -> Point #body
+            }
+        );
+
+        assert_eq!(
+            ":: core :: compile_error ! { \"`Out` is the alias of the output type, so it can't name anything else in the body.\" }",
+            tokens.to_string()
+        );
+    }
+}
+
+#[test]
+fn test_trait_generics_are_kept_by_impl_op_only() {
+    let tokens = crate::expand_op(
+        syn::parse_quote!(Shift<3>),
+        syn::parse_quote! {
+            fn shift(self: Bits, rhs: Bits) -> Bits {
+                Bits(self.0 << 3 | rhs.0)
+            }
+        }
+    ).to_string();
+
+    assert!(tokens.contains("impl Shift < 3 > for Bits"));
+
+    let tokens = crate::expand_ops(
+        syn::parse_quote!(Shift<3>),
+        syn::parse_quote! {
+            fn shift(self: Bits, rhs: Bits) -> Bits {
+                Bits(self.0 << 3 | rhs.0)
+            }
+        }
+    ).to_string();
+
+    assert!(tokens.contains("A trait path with generic arguments can only be implemented by `impl_op`"));
+}
+
+#[test]
+fn test_overlapping_implementations_are_rejected() {
+    let source: syn::ItemFn = syn::parse_quote! {
+        fn add(self: Meters, rhs: f64) -> Meters {
+            Meters(self.0 + rhs)
+        }
+    };
+
+    let tokens = crate::for_each_trait(syn::parse_quote!(Add, Sub, Add), source.clone(), crate::expand_ops).to_string();
+    assert!(tokens.contains("This trait is already listed."));
+
+    let tokens = crate::for_each_trait(syn::parse_quote!(Add; rhs_types(f64, &f64)), source.clone(), crate::expand_ops).to_string();
+    assert!(tokens.contains("This type is already implemented by the permutations borrowing `f64`."));
+
+    // `impl_op` implements exactly what it's given, so both are fine there.
+    let tokens = crate::for_each_trait(syn::parse_quote!(Add; rhs_types(f64, &f64)), source, crate::expand_op).to_string();
+    assert!(!tokens.contains("compile_error"));
+
+    let tokens = crate::expand_ops(
+        syn::parse_quote!(Add; commutative),
+        syn::parse_quote! {
+            fn add<T: Into<f64>>(self: Meters, rhs: T) -> Meters {
+                Meters(self.0 + rhs.into())
+            }
+        }
+    ).to_string();
+    assert!(tokens.contains("`commutative` requires `rhs` not to be a type parameter"));
+}
+
+#[test]
+fn test_same_type_operands_get_no_clone_bounds() {
+    let fns = expanded_fns(crate::expand_ops(
+        syn::parse_quote!(Mul),
+        syn::parse_quote! {
+            fn dot(self: Vec3, rhs: Vec3) -> f32 {
+                self.x * rhs.x + self.y * rhs.y + self.z * rhs.z
+            }
+        }
+    ));
+
+    assert_eq!(4, fns.len());
+
+    for item in &fns {
+        let tokens = item.to_token_stream().to_string();
+        assert!(!tokens.contains("Clone") && !tokens.contains("Copy") && !tokens.contains("clone"));
+    }
+}
+
+#[test]
+fn test_shl_shr_ops_require_both_functions() {
+    let tokens = crate::expand_shl_shr_ops(proc_macro2::TokenStream::new(), syn::parse_quote! {
+        mod shifts {
+            fn shl(self: Bits, rhs: u32) -> Bits { Bits(self.0 << rhs) }
+        }
+    }).to_string();
+    assert!(tokens.contains("Requires both of the functions `shl` and `shr`."));
+
+    let tokens = crate::expand_shl_shr_ops(proc_macro2::TokenStream::new(), syn::parse_quote! {
+        mod shifts {
+            fn shl(self: Bits, rhs: u32) -> Bits { Bits(self.0 << rhs) }
+            fn rotate(self: Bits, rhs: u32) -> Bits { Bits(self.0.rotate_left(rhs)) }
+        }
+    }).to_string();
+    assert!(tokens.contains("Only the functions `shl` and `shr` can be in the module, once each."));
+
+    let fns = expanded_fns(crate::expand_shl_shr_ops(quote::quote!(inline_refs), syn::parse_quote! {
+        #[cfg(feature = "shifts")]
+        mod shifts {
+            fn shr(self: Bits, rhs: u32) -> Bits { Bits(self.0 >> rhs) }
+            fn shl(self: Bits, rhs: u32) -> Bits { Bits(self.0 << rhs) }
+        }
+    }));
+
+    let attrs: Vec<Vec<String>> = fns.iter()
+        .map(|item| item.attrs.iter().map(|attr| attr.to_token_stream().to_string()).collect())
+        .collect();
+
+    assert_eq!(vec![
+        vec!["# [cfg (feature = \"shifts\")]".to_string(), "# [opimps :: impl_ops (:: core :: ops :: Shl ; inline_refs)]".to_string()],
+        vec!["# [cfg (feature = \"shifts\")]".to_string(), "# [opimps :: impl_ops (:: core :: ops :: Shr ; inline_refs)]".to_string()],
+    ], attrs);
+}
+
+#[test]
+fn test_trailing_commas_are_accepted() {
+    let args: crate::OpArgs = syn::parse_quote!(Add,);
+    assert!(args.more_traits.is_empty());
+
+    let args: crate::OpArgs = syn::parse_quote!(Add, Sub,);
+    assert_eq!(1, args.more_traits.len());
+
+    let args: crate::OpArgs = syn::parse_quote!(Add; strict, inline_refs,);
+    assert!(args.strict && args.inline_refs);
+
+    let args: crate::OpArgs = syn::parse_quote!(Shl; u8, u16,);
+    assert_eq!(2, args.rhs_types.len());
+
+    let _: crate::VecOpArgs = syn::parse_quote!(Add, field = 0,);
+    let _: crate::RefOpArgs = syn::parse_quote!(Row; lifetime = 'a,);
+
+    use syn::parse::Parser;
+    assert!(crate::args::parse_trait_path.parse2(quote::quote!(PartialEq,)).is_ok());
+    assert!(crate::args::parse_trait_path.parse2(quote::quote!(PartialEq, strict)).is_err());
+}
+
+#[test]
+fn test_impl_trait_output_is_rejected() {
+    let error = crate::check_nameable_output(&syn::parse_quote! {
+        fn compose(self: F, rhs: G) -> impl Fn(i32) -> i32
+    }).err().unwrap();
+    assert!(error.to_string().starts_with("The output can't be an `impl Trait`"));
+
+    let error = crate::check_nameable_output(&syn::parse_quote! {
+        fn chain(self: A, rhs: B) -> Option<impl Iterator<Item = u8>>
+    }).err().unwrap();
+    assert!(error.to_string().starts_with("The output can't be an `impl Trait`"));
+
+    assert!(crate::check_nameable_output(&syn::parse_quote! {
+        fn compose(self: F, rhs: G) -> Box<dyn Fn(i32) -> i32>
+    }).is_ok());
+}
+
+#[test]
+fn test_emit_debug_is_off_by_default_and_forwarded() {
+    let args: crate::OpArgs = syn::parse_quote!(Add);
+    assert!(!args.emit_debug);
+
+    let fns = expanded_fns(crate::expand_ops(
+        syn::parse_quote!(Add; emit_debug),
+        syn::parse_quote! {
+            fn add(self: Buffer, rhs: Buffer) -> Buffer {
+                Buffer(self.0 + rhs.0)
+            }
+        }
+    ));
+
+    assert!(fns.iter().all(|item| has_attr(item, "opimps :: impl_op (Add ; emit_debug)")));
+}
+
+#[test]
+fn test_boxed_operands_are_not_borrowed() {
+    let fns = expanded_fns(crate::expand_ops(
+        syn::parse_quote!(Add),
+        syn::parse_quote! {
+            fn add(self: Box<Node>, rhs: Box<Node>) -> Box<Node> {
+                Box::new(Node::Sum(self, rhs))
+            }
+        }
+    ));
+
+    assert_eq!(1, fns.len());
+    assert!(!has_borrowed_operand(&fns[0]));
+
+    let fns = expanded_fns(crate::expand_ops(
+        syn::parse_quote!(Mul),
+        syn::parse_quote! {
+            fn mul(self: Box<Node>, rhs: i32) -> Box<Node> {
+                Box::new(Node::Scaled(self, *rhs))
+            }
+        }
+    ));
+
+    let params: Vec<String> = fns.iter().map(|item| item.sig.inputs.to_token_stream().to_string()).collect();
+    assert_eq!(vec!["self : Box < Node > , rhs : i32", "self : Box < Node > , rhs : & i32"], params);
+}
+
+#[test]
+fn test_self_in_the_output_is_the_type_of_self() {
+    let fns = expanded_fns(crate::expand_ops(
+        syn::parse_quote!(Sub),
+        syn::parse_quote! {
+            fn sub(self: Point, rhs: Point) -> Option<Self> {
+                Some(Point(self.0.checked_sub(rhs.0)?, self.1.checked_sub(rhs.1)?))
+            }
+        }
+    ));
+
+    for item in &fns {
+        assert_eq!("-> Option < Point >", item.sig.output.to_token_stream().to_string());
+    }
+}
+
+#[test]
+fn test_rhs_is_explicit_in_every_permutation() {
+    let fns = expanded_fns(crate::expand_ops(
+        syn::parse_quote!(Add),
+        syn::parse_quote! {
+            fn add(self: Point, rhs: Point) -> Point {
+                Point(self.0 + rhs.0)
+            }
+        }
+    ));
+
+    let headers: Vec<String> = fns.into_iter()
+        .map(|mut item| {
+            let args = item.attrs.pop().unwrap().parse_args().unwrap();
+            impl_header(crate::expand_op(args, item))
+        })
+        .collect();
+
+    assert_eq!(vec![
+        "Add < Point > for Point",
+        "Add < & Point > for & Point",
+        "Add < & Point > for Point",
+        "Add < Point > for & Point",
+    ], headers);
+}
+
+#[test]
+fn test_prim_only_implements_the_owned_rhs() {
+    let fns = expanded_fns(crate::expand_ops_assign(
+        syn::parse_quote!(AddAssign; prim),
+        syn::parse_quote! {
+            fn add_assign(self: Cell, rhs: i32) {
+                self.0 += rhs;
+            }
+        }
+    ));
+
+    assert_eq!(1, fns.len());
+    assert!(!has_borrowed_operand(&fns[0]));
+
+    let tokens = crate::expand_ops_assign(
+        syn::parse_quote!(AddAssign; prim),
+        syn::parse_quote! {
+            fn add_assign(self: Cell, rhs: Cell) {
+                self.0 += rhs.0;
+            }
+        }
+    );
+
+    assert_eq!(":: core :: compile_error ! { \"`prim` requires a primitive right-hand side, e.g. `i32`.\" }", tokens.to_string());
+}
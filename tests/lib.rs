@@ -1,3 +1,8 @@
+#![cfg_attr(feature = "nightly", feature(generic_const_exprs))]
+#![cfg_attr(feature = "nightly", allow(incomplete_features))]
+// The generics tests implement operators for the shared `Dummy` from inside the tests.
+#![allow(non_local_definitions)]
+
 #[cfg(test)]
 mod tests {
     struct Dummy<T>(T);
@@ -113,4 +118,2643 @@ mod tests {
         let res = a + b;
         assert_eq!(5.0, res.0);
     }
+
+    #[test]
+    fn test_impl_uni_op_consuming_projection() {
+        trait IntoInner {
+            type Output;
+            fn into_inner(self) -> Self::Output;
+        }
+
+        struct Wrapper(String);
+
+        #[opimps::impl_uni_op(IntoInner)]
+        fn into_inner(self: Wrapper) -> String {
+            self.0
+        }
+
+        let a = Wrapper(String::from("inner"));
+        let res = a.into_inner();
+
+        assert_eq!("inner", res);
+    }
+
+    #[test]
+    fn test_impl_uni_ops_consuming_projection_clones_borrowed() {
+        trait IntoInner {
+            type Output;
+            fn into_inner(self) -> Self::Output;
+        }
+
+        struct Wrapper(String);
+
+        #[opimps::impl_uni_ops(IntoInner)]
+        fn into_inner(self: Wrapper) -> String {
+            self.0.clone()
+        }
+
+        let a = Wrapper(String::from("inner"));
+        let res = (&a).into_inner();
+
+        assert_eq!("inner", res);
+        assert_eq!("inner", a.0);
+
+        let res = a.into_inner();
+        assert_eq!("inner", res);
+    }
+
+    #[test]
+    fn test_generics_with_inline_outlives_bound() {
+        use std::ops::Add;
+
+        struct Ref<'a, T> {
+            val: &'a T
+        }
+
+        #[opimps::impl_ops(Add)]
+        fn add<'a, T: 'a>(self: Ref<'a, T>, rhs: Ref<'a, T>) -> T where T: Add<Output = T> + Copy {
+            *self.val + *rhs.val
+        }
+
+        let a = 5;
+        let b = 7;
+
+        let a_ref = Ref { val: &a };
+        let b_ref = Ref { val: &b };
+
+        assert_eq!(12, &a_ref + &b_ref);
+        assert_eq!(12, &a_ref + Ref { val: &b });
+        assert_eq!(12, Ref { val: &a } + &b_ref);
+        assert_eq!(12, a_ref + b_ref);
+    }
+
+    #[test]
+    fn test_visibility_is_ignored_without_strict() {
+        use std::ops::Add;
+
+        struct Num(i32);
+
+        #[opimps::impl_ops(Add)]
+        pub fn add(self: Num, rhs: Num) -> i32 {
+            self.0 + rhs.0
+        }
+
+        assert_eq!(5, Num(2) + Num(3));
+    }
+
+    #[test]
+    fn test_strict_without_visibility() {
+        use std::ops::Add;
+
+        struct Num(i32);
+
+        #[opimps::impl_ops(Add; strict)]
+        fn add(self: Num, rhs: Num) -> i32 {
+            self.0 + rhs.0
+        }
+
+        assert_eq!(5, &Num(2) + Num(3));
+    }
+
+    #[test]
+    fn test_impl_op_fluent_mut_receiver() {
+        trait FluentAdd<Rhs> {
+            fn fluent_add(&mut self, rhs: Rhs) -> &mut Self;
+        }
+
+        struct Acc(i64);
+
+        #[opimps::impl_op(FluentAdd)]
+        fn fluent_add(self: &mut Acc, rhs: i64) -> &mut Self {
+            self.0 += rhs;
+            self
+        }
+
+        #[opimps::impl_op(FluentAdd)]
+        fn fluent_add(self: &mut Acc, rhs: &Acc) -> &mut Self {
+            self.0 += rhs.0;
+            self
+        }
+
+        let delta = Acc(10);
+        let mut acc = Acc(1);
+        acc.fluent_add(2).fluent_add(&delta).fluent_add(3);
+
+        assert_eq!(16, acc.0);
+        assert_eq!(10, delta.0);
+    }
+
+    #[test]
+    fn test_impl_vec_ops_panic() {
+        use std::ops::Add;
+
+        #[opimps::impl_vec_ops(Add, field = 0, on_mismatch = panic)]
+        struct DVec(Vec<f64>);
+
+        let a = DVec(vec![1.0, 2.0, 3.0]);
+        let b = DVec(vec![4.0, 5.0, 6.0]);
+
+        assert_eq!(vec![5.0, 7.0, 9.0], (&a + &b).0);
+        assert_eq!(vec![5.0, 7.0, 9.0], (&a + DVec(vec![4.0, 5.0, 6.0])).0);
+        assert_eq!(vec![5.0, 7.0, 9.0], (DVec(vec![1.0, 2.0, 3.0]) + &b).0);
+        assert_eq!(vec![5.0, 7.0, 9.0], (a + b).0);
+
+        let res = std::panic::catch_unwind(|| DVec(vec![1.0]) + DVec(vec![1.0, 2.0]));
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_impl_vec_ops_result() {
+        use std::ops::Mul;
+
+        #[derive(Debug, PartialEq)]
+        struct LenErr {
+            lhs: usize,
+            rhs: usize
+        }
+
+        impl From<(usize, usize)> for LenErr {
+            fn from((lhs, rhs): (usize, usize)) -> Self {
+                LenErr { lhs, rhs }
+            }
+        }
+
+        #[opimps::impl_vec_ops(Mul, field = values, on_mismatch = result(LenErr))]
+        struct DVec<T> {
+            values: Vec<T>
+        }
+
+        let a = DVec { values: vec![1, 2, 3] };
+        let b = DVec { values: vec![4, 5, 6] };
+        let c = DVec { values: vec![4, 5] };
+
+        assert_eq!(vec![4, 10, 18], (&a * &b).ok().unwrap().values);
+        assert_eq!(Some(LenErr { lhs: 3, rhs: 2 }), (&a * &c).err());
+        assert_eq!(Some(LenErr { lhs: 2, rhs: 3 }), (&c * a).err());
+        assert_eq!(Some(LenErr { lhs: 3, rhs: 2 }), (b * c).err());
+    }
+
+    #[test]
+    fn test_impl_vec_ops_generic_error_type() {
+        use std::marker::PhantomData;
+        use std::ops::Sub;
+
+        #[derive(Debug, PartialEq)]
+        struct LenErr<T>(usize, usize, PhantomData<T>);
+
+        impl<T> From<(usize, usize)> for LenErr<T> {
+            fn from((lhs, rhs): (usize, usize)) -> Self {
+                LenErr(lhs, rhs, PhantomData)
+            }
+        }
+
+        #[opimps::impl_vec_ops(Sub, field = 0, on_mismatch = result(LenErr<u8>))]
+        struct Bytes(Vec<u8>);
+
+        assert_eq!(vec![1, 2], (Bytes(vec![3, 5]) - Bytes(vec![2, 3])).ok().unwrap().0);
+        assert_eq!(Some(LenErr(2, 1, PhantomData)), (Bytes(vec![3, 5]) - Bytes(vec![2])).err());
+    }
+
+    #[test]
+    fn test_impl_op_ref_row_projection() {
+        trait Row<Rhs> {
+            type Output;
+            fn row(self, rhs: Rhs) -> Self::Output;
+        }
+
+        struct Matrix {
+            cols: usize,
+            data: Vec<f64>
+        }
+
+        #[opimps::impl_op_ref(Row; lifetime = 'a, borrow = self)]
+        fn row<'a>(self: &'a Matrix, i: usize) -> &'a [f64] {
+            &self.data[i * self.cols..(i + 1) * self.cols]
+        }
+
+        let m = Matrix { cols: 3, data: vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0] };
+
+        let first = (&m).row(0);
+        let second = m.row(1);
+
+        assert_eq!(&[1.0, 2.0, 3.0], first);
+        assert_eq!(&[4.0, 5.0, 6.0], second);
+    }
+
+    #[test]
+    fn test_impl_op_ref_borrow_rhs() {
+        trait Lookup<Rhs> {
+            type Output;
+            fn lookup(self, rhs: Rhs) -> Self::Output;
+        }
+
+        struct Key(usize);
+
+        #[opimps::impl_op_ref(Lookup; lifetime = 'm, borrow = rhs)]
+        fn lookup(self: Key, rhs: &Vec<String>) -> &str {
+            &rhs[self.0]
+        }
+
+        let names = vec![String::from("a"), String::from("b")];
+
+        assert_eq!("b", Key(1).lookup(&names));
+    }
+
+    #[test]
+    fn test_impl_ops_multiple_rhs_on_same_lhs() {
+        use std::ops::Sub;
+
+        #[derive(Debug, PartialEq)]
+        struct Point(f64, f64);
+
+        #[derive(Debug, PartialEq)]
+        struct Vector(f64, f64);
+
+        #[opimps::impl_ops(Sub)]
+        fn sub(self: Point, rhs: Point) -> Vector {
+            Vector(self.0 - rhs.0, self.1 - rhs.1)
+        }
+
+        #[opimps::impl_ops(Sub)]
+        fn sub(self: Point, rhs: Vector) -> Point {
+            Point(self.0 - rhs.0, self.1 - rhs.1)
+        }
+
+        let a = Point(5.0, 7.0);
+        let b = Point(2.0, 3.0);
+        let v = Vector(1.0, 1.0);
+
+        assert_eq!(Vector(3.0, 4.0), &a - &b);
+        assert_eq!(Point(4.0, 6.0), &a - &v);
+        assert_eq!(Vector(3.0, 4.0), &a - Point(2.0, 3.0));
+        assert_eq!(Point(4.0, 6.0), &a - Vector(1.0, 1.0));
+        assert_eq!(Vector(3.0, 4.0), Point(5.0, 7.0) - &b);
+        assert_eq!(Point(4.0, 6.0), Point(5.0, 7.0) - &v);
+        assert_eq!(Vector(3.0, 4.0), a - b);
+        assert_eq!(Point(1.0, 2.0), Point(2.0, 3.0) - v);
+    }
+
+    #[test]
+    fn test_impl_ops_inline_refs() {
+        use std::ops::Add;
+
+        struct Buffer(Vec<u8>);
+
+        #[opimps::impl_ops(Add; inline_refs)]
+        fn add(self: Buffer, rhs: Buffer) -> usize {
+            self.0.len() + rhs.0.len()
+        }
+
+        let a = Buffer(vec![1, 2]);
+        let b = Buffer(vec![3]);
+
+        assert_eq!(3, &a + &b);
+        assert_eq!(3, a + b);
+    }
+
+    #[test]
+    fn test_impl_ops_clone_refs() {
+        use std::ops::Add;
+
+        struct NotClone(Vec<u8>);
+
+        #[derive(Clone)]
+        struct Wrapper<T>(Vec<T>);
+
+        #[opimps::impl_ops(Add; clone_refs)]
+        fn add<T>(self: Wrapper<T>, rhs: Wrapper<T>) -> Wrapper<T> {
+            let mut res = self.0;
+            res.extend(rhs.0);
+            Wrapper(res)
+        }
+
+        let a = Wrapper(vec![NotClone(vec![1])]);
+        let b = Wrapper(vec![NotClone(vec![2])]);
+        let res = a + b;
+        assert_eq!(vec![vec![1], vec![2]], res.0.into_iter().map(|v| v.0).collect::<Vec<_>>());
+
+        let a = Wrapper(vec![1, 2]);
+        let b = Wrapper(vec![3]);
+
+        assert_eq!(vec![1, 2, 3], (&a + &b).0);
+        assert_eq!(vec![1, 2, 3], (&a + Wrapper(vec![3])).0);
+        assert_eq!(vec![1, 2, 3], (Wrapper(vec![1, 2]) + &b).0);
+        assert_eq!(vec![1, 2], a.0);
+        assert_eq!(vec![3], b.0);
+    }
+
+    #[test]
+    fn test_impl_ops_sealed_trait() {
+        mod ops {
+            mod private {
+                pub trait Sealed {}
+            }
+
+            pub trait Combine<Rhs = Self>: private::Sealed {
+                type Output;
+                fn combine(self, rhs: Rhs) -> Self::Output;
+            }
+
+            pub struct Num(pub i32);
+
+            // The seal has to cover every self type generated by `impl_ops`, both `T` and `&T`.
+            impl private::Sealed for Num {}
+            impl private::Sealed for &Num {}
+
+            #[opimps::impl_ops(Combine)]
+            fn combine(self: Num, rhs: Num) -> i32 {
+                self.0 * 10 + rhs.0
+            }
+        }
+
+        use ops::{Combine, Num};
+
+        let a = Num(1);
+        let b = Num(2);
+
+        assert_eq!(12, (&a).combine(&b));
+        assert_eq!(12, (&a).combine(Num(2)));
+        assert_eq!(12, Num(1).combine(&b));
+        assert_eq!(12, a.combine(b));
+    }
+
+    #[test]
+    fn test_impl_ops_generic_rhs() {
+        use std::ops::Add;
+
+        struct Matrix(i32);
+
+        impl From<i32> for Matrix {
+            fn from(val: i32) -> Self {
+                Matrix(val)
+            }
+        }
+
+        impl From<&str> for Matrix {
+            fn from(val: &str) -> Self {
+                Matrix(val.parse().unwrap())
+            }
+        }
+
+        #[opimps::impl_ops(Add)]
+        fn add<Rhs: Into<Matrix>>(self: Matrix, rhs: Rhs) -> Matrix {
+            Matrix(self.0 + rhs.into().0)
+        }
+
+        let a = Matrix(1);
+        let b = 2;
+
+        assert_eq!(3, (&a + b).0);
+        assert_eq!(5, (&a + "4").0);
+        assert_eq!(4, (&a + Matrix(3)).0);
+        assert_eq!(3, (a + b).0);
+    }
+
+    #[test]
+    fn test_impl_op_out_alias() {
+        use std::ops::Mul;
+
+        struct Vec2(f64, f64);
+
+        struct Outer {
+            x: f64,
+            y: f64
+        }
+
+        #[opimps::impl_op(Mul; out_alias = Out)]
+        fn mul(self: Vec2, rhs: Vec2) -> Outer {
+            let res: Out = Out { x: self.0 * rhs.0, y: self.1 * rhs.1 };
+            res
+        }
+
+        let res = Vec2(1.0, 2.0) * Vec2(3.0, 4.0);
+        assert_eq!(3.0, res.x);
+        assert_eq!(8.0, res.y);
+    }
+
+    #[test]
+    fn test_impl_ops_out_alias_and_self_output() {
+        use std::ops::{Add, Sub};
+
+        struct Num<T>(T);
+
+        struct Pair<T> {
+            lhs: T,
+            rhs: T
+        }
+
+        impl<T> Pair<T> {
+            fn new(lhs: T, rhs: T) -> Self {
+                Pair { lhs, rhs }
+            }
+        }
+
+        #[opimps::impl_ops(Add; out_alias = Out)]
+        fn add<T: Copy>(self: Num<T>, rhs: Num<T>) -> Pair<T> {
+            Out::new(self.0, rhs.0)
+        }
+
+        #[opimps::impl_ops(Sub)]
+        fn sub<T: Copy>(self: Num<T>, rhs: Num<T>) -> Pair<T> {
+            Self::Output { lhs: self.0, rhs: rhs.0 }
+        }
+
+        let a = Num(1);
+        let b = Num(2);
+
+        let res = &a + &b;
+        assert_eq!((1, 2), (res.lhs, res.rhs));
+
+        let res = &a - &b;
+        assert_eq!((1, 2), (res.lhs, res.rhs));
+
+        let res = a + b;
+        assert_eq!((1, 2), (res.lhs, res.rhs));
+    }
+
+    mod gen_test {
+        use std::ops::{Add, Mul};
+
+        #[derive(Clone, Debug, Default, PartialEq)]
+        pub struct Matrix(pub [i32; 4]);
+
+        impl Matrix {
+            pub fn identity() -> Self {
+                Matrix([1, 0, 0, 1])
+            }
+        }
+
+        #[opimps::impl_ops(Mul; gen_test, sample = Matrix::identity())]
+        fn mul(self: Matrix, rhs: Matrix) -> Matrix {
+            let (l, r) = (self.0, rhs.0);
+            Matrix([
+                l[0] * r[0] + l[1] * r[2], l[0] * r[1] + l[1] * r[3],
+                l[2] * r[0] + l[3] * r[2], l[2] * r[1] + l[3] * r[3]
+            ])
+        }
+
+        #[opimps::impl_ops(Mul; gen_test, sample = Matrix::identity(), rhs_sample = 3)]
+        fn mul(self: Matrix, rhs: i32) -> Matrix {
+            Matrix(self.0.map(|v| v * rhs))
+        }
+
+        #[opimps::impl_ops(Add; gen_test)]
+        fn add(self: Matrix, rhs: Matrix) -> Matrix {
+            Matrix([self.0[0] + rhs.0[0], self.0[1] + rhs.0[1], self.0[2] + rhs.0[2], self.0[3] + rhs.0[3]])
+        }
+
+        #[test]
+        fn test_gen_test_generates_permutation_tests() {
+            __opimps_test_Mul_Matrix_Matrix::permutations_agree();
+            __opimps_test_Mul_Matrix_i32::permutations_agree();
+            __opimps_test_Add_Matrix_Matrix::permutations_agree();
+        }
+    }
+
+    #[test]
+    fn test_impl_op_arena_lifetime_operands() {
+        use std::ops::Add;
+
+        #[derive(Debug, PartialEq)]
+        enum Node {
+            Num(i64),
+            Sum(i64, i64)
+        }
+
+        impl Node {
+            fn value(&self) -> i64 {
+                match self {
+                    Node::Num(v) => *v,
+                    Node::Sum(l, r) => l + r
+                }
+            }
+        }
+
+        #[opimps::impl_op(Add)]
+        fn add<'arena>(self: &'arena Node, rhs: &'arena Node) -> Node {
+            Node::Sum(self.value(), rhs.value())
+        }
+
+        let arena = [Node::Num(2), Node::Num(3)];
+        let (a, b): (&Node, &Node) = (&arena[0], &arena[1]);
+
+        let res = a + b;
+
+        assert_eq!(Node::Sum(2, 3), res);
+        assert_eq!(Node::Num(2), arena[0]);
+    }
+
+    #[test]
+    fn test_impl_ops_deref_operands_enum_match() {
+        use std::ops::Add;
+
+        #[derive(Debug, PartialEq)]
+        enum Value {
+            Int(i64),
+            Text(String),
+            List(Vec<i64>)
+        }
+
+        #[opimps::impl_ops(Add; deref_operands)]
+        fn add(self: Value, rhs: Value) -> Value {
+            match (self, rhs) {
+                (Value::Int(l), Value::Int(r)) => Value::Int(l + r),
+                (Value::Text(l), Value::Text(r)) => Value::Text(format!("{}{}", l, r)),
+                (Value::List(l), Value::List(r)) => Value::List(l.iter().chain(r).copied().collect()),
+                (l, r) => panic!("Can't add {:?} and {:?}", l, r)
+            }
+        }
+
+        let a = Value::Text(String::from("ab"));
+        let b = Value::Text(String::from("cd"));
+
+        assert_eq!(Value::Text(String::from("abcd")), &a + &b);
+        assert_eq!(Value::Text(String::from("abcd")), &a + Value::Text(String::from("cd")));
+        assert_eq!(Value::Text(String::from("abcd")), Value::Text(String::from("ab")) + &b);
+        assert_eq!(Value::Text(String::from("abcd")), a + b);
+
+        assert_eq!(Value::Int(3), Value::Int(1) + Value::Int(2));
+        assert_eq!(Value::List(vec![1, 2]), Value::List(vec![1]) + &Value::List(vec![2]));
+    }
+
+    #[cfg(feature = "nightly")]
+    mod const_exprs {
+        use std::ops::Add;
+
+        pub struct Stack<const N: usize>(pub [i32; N]);
+
+        #[opimps::impl_ops_rprim(Add)]
+        fn add<const N: usize>(self: Stack<N>, rhs: i32) -> Stack<{N + 1}> where [(); N + 1]: {
+            let mut items = [rhs; N + 1];
+            items[..N].copy_from_slice(&self.0);
+            Stack(items)
+        }
+
+        #[test]
+        fn test_impl_op_const_expr_output() {
+            let stack = Stack([1, 2]);
+            assert_eq!([1, 2, 3], (&stack + 3).0);
+
+            let stack = stack + 3;
+            assert_eq!([1, 2, 3], stack.0);
+        }
+    }
+
+    #[test]
+    fn test_impl_option_ops_merge_configs() {
+        trait Merge<Rhs = Self> {
+            type Output;
+            fn merge(self, rhs: Rhs) -> Self::Output;
+        }
+
+        #[derive(Clone, Debug, PartialEq)]
+        struct Config {
+            name: String,
+            verbose: bool
+        }
+
+        fn combine(lhs: Config, rhs: Config) -> Config {
+            Config { name: format!("{}+{}", lhs.name, rhs.name), verbose: lhs.verbose || rhs.verbose }
+        }
+
+        #[opimps::impl_option_ops(Merge, inner_merge = combine)]
+        fn merge(self: Option<Config>, rhs: Option<Config>) -> Option<Config> {}
+
+        let a = Some(Config { name: String::from("a"), verbose: false });
+        let b = Some(Config { name: String::from("b"), verbose: true });
+        let merged = Some(Config { name: String::from("a+b"), verbose: true });
+
+        assert_eq!(merged, (&a).merge(&b));
+        assert_eq!(merged, (&a).merge(b.clone()));
+        assert_eq!(merged, a.clone().merge(&b));
+        assert_eq!(a, (&a).merge(None));
+        assert_eq!(b, None.merge(&b));
+        assert_eq!(None, None::<Config>.merge(None));
+        assert_eq!(merged, a.merge(b));
+    }
+
+    #[test]
+    fn test_impl_ops_rhs_fn_closure() {
+        trait Map<F> {
+            type Output;
+            fn map(self, rhs: F) -> Self::Output;
+        }
+
+        struct Numbers(Vec<i32>);
+
+        #[opimps::impl_ops(Map; rhs_fn = "Fn(i32) -> i32")]
+        fn map(self: Numbers, rhs: _) -> Numbers {
+            Numbers(self.0.iter().map(|v| rhs(*v)).collect())
+        }
+
+        let offset = 10;
+        let a = Numbers(vec![1, 2, 3]);
+
+        assert_eq!(vec![2, 4, 6], (&a).map(|v| v * 2).0);
+        assert_eq!(vec![11, 12, 13], a.map(|v| v + offset).0);
+    }
+
+
+    #[test]
+    fn test_impl_ops_newtype_projects_without_cloning() {
+        trait Join<Rhs = Self> {
+            type Output;
+            fn join(self, rhs: Rhs) -> Self::Output;
+        }
+
+        #[opimps::impl_ops(Join)]
+        fn join(self: String, rhs: String) -> String {
+            format!("{} {}", self, rhs)
+        }
+
+        // Neither `Clone` nor `Copy`, so the borrowed permutations can't clone it.
+        #[repr(transparent)]
+        #[derive(Debug, PartialEq)]
+        struct Name(String);
+
+        #[opimps::impl_ops(Join; newtype = 0)]
+        fn join(self: Name, rhs: Name) -> Name {
+            Name(Join::join(self.0, rhs.0))
+        }
+
+        let a = Name(String::from("Ada"));
+        let b = Name(String::from("Lovelace"));
+
+        assert_eq!(Name(String::from("Ada Lovelace")), (&a).join(&b));
+        assert_eq!(Name(String::from("Ada Lovelace")), (&a).join(Name(String::from("Lovelace"))));
+        assert_eq!(Name(String::from("Lovelace Ada")), Name(String::from("Lovelace")).join(&a));
+        assert_eq!(Name(String::from("Ada Lovelace")), a.join(b));
+    }
+
+
+    #[test]
+    fn test_impl_ops_matmul_output_mixes_operand_generics() {
+        use std::ops::Mul;
+
+        #[derive(Debug, PartialEq)]
+        struct Matrix<const M: usize, const N: usize>([[i32; N]; M]);
+
+        #[opimps::impl_ops(Mul)]
+        fn mul<const M: usize, const N: usize, const P: usize>(self: Matrix<M, N>, rhs: Matrix<N, P>) -> Matrix<M, P> {
+            let mut out = [[0; P]; M];
+            for (i, row) in out.iter_mut().enumerate() {
+                for (j, cell) in row.iter_mut().enumerate() {
+                    *cell = (0..N).map(|k| self.0[i][k] * rhs.0[k][j]).sum();
+                }
+            }
+            Matrix(out)
+        }
+
+        let a = Matrix([[1, 2, 3], [4, 5, 6]]);
+        let b = Matrix([[1], [0], [2]]);
+        let expected = Matrix([[7], [16]]);
+
+        let owned: Matrix<2, 1> = Matrix([[1, 2, 3], [4, 5, 6]]) * Matrix([[1], [0], [2]]);
+        assert_eq!(expected, owned);
+        assert_eq!(expected, &a * &b);
+        assert_eq!(expected, Matrix([[1, 2, 3], [4, 5, 6]]) * &b);
+        assert_eq!(expected, &a * Matrix([[1], [0], [2]]));
+    }
+
+
+    #[test]
+    #[should_panic(expected = "money overflow: Overflow")]
+    fn test_impl_ops_panics_on_err_message() {
+        use std::ops::Add;
+
+        #[derive(Debug, PartialEq)]
+        struct Money(u8);
+
+        #[derive(Debug)]
+        struct Overflow;
+
+        #[opimps::impl_ops(Add; panics_on_err = "money overflow")]
+        fn add(self: Money, rhs: Money) -> Money {
+            self.0.checked_add(rhs.0).map(Money).ok_or(Overflow)
+        }
+
+        let a = Money(200);
+        let b = Money(50);
+        assert_eq!(Money(250), &a + &b);
+        assert_eq!(Money(250), &a + Money(50));
+        assert_eq!(Money(250), Money(200) + &b);
+
+        let _ = a + Money(100);
+    }
+
+
+    #[test]
+    fn test_impl_ops_drop_counts() {
+        use std::cell::Cell;
+        use std::ops::Add;
+        use std::rc::Rc;
+
+        struct Tracked {
+            value: i32,
+            drops: Rc<Cell<usize>>,
+        }
+
+        impl Drop for Tracked {
+            fn drop(&mut self) {
+                self.drops.set(self.drops.get() + 1);
+            }
+        }
+
+        // Fields can't be moved out of a type implementing `Drop`, so the body reads them.
+        #[opimps::impl_ops(Add)]
+        fn add(self: Tracked, rhs: Tracked) -> Tracked {
+            Tracked { value: self.value + rhs.value, drops: Rc::clone(&self.drops) }
+        }
+
+        let drops = Rc::new(Cell::new(0));
+        let tracked = |value| Tracked { value, drops: Rc::clone(&drops) };
+
+        let a = tracked(1);
+        let b = tracked(2);
+
+        let sum = &a + &b;
+        assert_eq!(3, sum.value);
+        assert_eq!(0, drops.get());
+        drop(sum);
+        assert_eq!(1, drops.get());
+
+        let sum = &a + tracked(2);
+        assert_eq!(3, sum.value);
+        assert_eq!(2, drops.get());
+        drop(sum);
+        assert_eq!(3, drops.get());
+
+        let sum = tracked(1) + &b;
+        assert_eq!(3, sum.value);
+        assert_eq!(4, drops.get());
+        drop(sum);
+        assert_eq!(5, drops.get());
+
+        let sum = a + b;
+        assert_eq!(3, sum.value);
+        assert_eq!(7, drops.get());
+        drop(sum);
+        assert_eq!(8, drops.get());
+    }
+
+
+    #[test]
+    fn test_impl_ops_hashmap_merge() {
+        use std::cell::Cell;
+        use std::collections::HashMap;
+        use std::hash::Hash;
+        use std::ops::Add;
+
+        thread_local! {
+            static CLONES: Cell<usize> = const { Cell::new(0) };
+        }
+
+        #[derive(Debug, PartialEq, Eq, Hash)]
+        struct Word(&'static str);
+
+        impl Clone for Word {
+            fn clone(&self) -> Self {
+                CLONES.with(|clones| clones.set(clones.get() + 1));
+                Word(self.0)
+            }
+        }
+
+        #[derive(Debug, PartialEq)]
+        struct Counts<K: Hash + Eq>(HashMap<K, u64>);
+
+        #[opimps::impl_ops(Add)]
+        fn add<K: Clone + Hash + Eq>(self: Counts<K>, rhs: Counts<K>) -> Counts<K> {
+            let mut merged = HashMap::new();
+            for (key, count) in self.0.iter().chain(rhs.0.iter()) {
+                *merged.entry(key.clone()).or_insert(0) += count;
+            }
+            Counts(merged)
+        }
+
+        let counts = |entries: &[(&'static str, u64)]| {
+            Counts(entries.iter().map(|(word, count)| (Word(word), *count)).collect::<HashMap<_, _>>())
+        };
+
+        let a = counts(&[("a", 1), ("b", 2)]);
+        let b = counts(&[("b", 3), ("c", 4)]);
+        let expected = counts(&[("a", 1), ("b", 5), ("c", 4)]);
+
+        // Only the keys are cloned, once per entry of either operand.
+        let clones = || CLONES.with(Cell::get);
+
+        assert_eq!(expected, &a + &b);
+        assert_eq!(4, clones());
+        assert_eq!(expected, &a + counts(&[("b", 3), ("c", 4)]));
+        assert_eq!(8, clones());
+        assert_eq!(expected, counts(&[("a", 1), ("b", 2)]) + &b);
+        assert_eq!(12, clones());
+        assert_eq!(expected, a + b);
+        assert_eq!(16, clones());
+    }
+
+
+    #[test]
+    fn test_impl_ops_rhs_form() {
+        use std::ops::{Add, Sub};
+
+        #[derive(Debug, PartialEq)]
+        struct Meters(f64);
+
+        #[opimps::impl_ops(Add; rhs_form = self)]
+        fn add(self: Meters, rhs: Meters) -> Meters {
+            Meters(self.0 + rhs.0)
+        }
+
+        #[opimps::impl_ops(Sub; rhs_form = omitted)]
+        fn sub(self: Meters, rhs: Meters) -> Meters {
+            Meters(self.0 - rhs.0)
+        }
+
+        let a = Meters(3.0);
+        let b = Meters(1.0);
+
+        assert_eq!(Meters(4.0), &a + &b);
+        assert_eq!(Meters(4.0), &a + Meters(1.0));
+        assert_eq!(Meters(2.0), Meters(3.0) - &b);
+        assert_eq!(Meters(2.0), a - b);
+    }
+
+
+    #[test]
+    fn test_impl_ops_generic_rhs_into_self() {
+        use std::ops::{Add, Sub};
+
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        struct Meters(f64);
+
+        impl From<f64> for Meters {
+            fn from(value: f64) -> Self {
+                Meters(value)
+            }
+        }
+
+        #[opimps::impl_ops(Add)]
+        fn add<R: Into<Self>>(self: Meters, rhs: R) -> Meters {
+            Meters(self.0 + rhs.into().0)
+        }
+
+        #[opimps::impl_ops(Sub)]
+        fn sub<R>(self: Meters, rhs: R) -> Meters where R: Into<Self> {
+            Meters(self.0 - rhs.into().0)
+        }
+
+        let a = Meters(3.0);
+
+        assert_eq!(Meters(4.0), a + 1.0);
+        assert_eq!(Meters(4.0), &a + 1.0);
+        assert_eq!(Meters(5.0), a + Meters(2.0));
+        assert_eq!(Meters(2.0), &a - 1.0);
+        assert_eq!(Meters(1.0), a - Meters(2.0));
+    }
+
+
+    #[test]
+    fn test_impl_ops_push_field_builder() {
+        use std::ops::Add;
+
+        #[derive(Debug, Clone, PartialEq)]
+        struct Clause(&'static str);
+
+        #[derive(Debug, Clone, PartialEq)]
+        struct Query {
+            table: &'static str,
+            clauses: Vec<Clause>,
+        }
+
+        // The generated body's variables don't clash with the name of `rhs`.
+        #[opimps::impl_ops(Add; push_field = clauses)]
+        fn add(self: Query, lhs: Clause) -> Query {}
+
+        let query = Query { table: "users", clauses: Vec::new() };
+        let active = Clause("active = 1");
+
+        let built = query.clone() + Clause("age > 18") + &active;
+        assert_eq!(vec![Clause("age > 18"), Clause("active = 1")], built.clauses);
+        assert_eq!("users", built.table);
+
+        let from_ref = &query + &active;
+        assert_eq!(vec![Clause("active = 1")], from_ref.clauses);
+        assert_eq!(vec![Clause("active = 1")], (&query + Clause("active = 1")).clauses);
+        assert!(query.clauses.is_empty());
+    }
+
+
+    #[test]
+    fn test_impl_ops_smart_pointer_outputs() {
+        use std::ops::{Add, Mul};
+        use std::rc::Rc;
+        use std::sync::Arc;
+
+        #[derive(Debug, PartialEq)]
+        struct Node {
+            value: i32,
+        }
+
+        #[opimps::impl_ops(Add)]
+        fn add(self: Node, rhs: Node) -> Rc<Node> {
+            Rc::new(Node { value: self.value + rhs.value })
+        }
+
+        #[opimps::impl_ops(Mul)]
+        fn mul(self: Node, rhs: Node) -> Arc<Node> {
+            Arc::new(Node { value: self.value * rhs.value })
+        }
+
+        let a = Node { value: 2 };
+        let b = Node { value: 3 };
+
+        let sum: Rc<Node> = &a + &b;
+        assert_eq!(5, sum.value);
+        assert_eq!(Rc::new(Node { value: 5 }), &a + Node { value: 3 });
+        assert_eq!(Rc::new(Node { value: 5 }), Node { value: 2 } + &b);
+
+        let product: Arc<Node> = &a * &b;
+        assert_eq!(6, product.value);
+        assert_eq!(Arc::new(Node { value: 6 }), &a * Node { value: 3 });
+        assert_eq!(Arc::new(Node { value: 6 }), Node { value: 2 } * &b);
+        assert_eq!(Arc::new(Node { value: 6 }), a * b);
+    }
+
+
+    #[test]
+    fn test_impl_ops_cfg_gated_bodies() {
+        use std::ops::Add;
+
+        #[derive(Debug, PartialEq)]
+        struct Lanes([u32; 4]);
+
+        // Only one of the implementations is compiled, so they don't overlap.
+        #[cfg(target_pointer_width = "64")]
+        #[opimps::impl_ops(Add)]
+        fn add(self: Lanes, rhs: Lanes) -> Lanes {
+            Lanes(std::array::from_fn(|i| self.0[i].wrapping_add(rhs.0[i])))
+        }
+
+        #[cfg(not(target_pointer_width = "64"))]
+        #[opimps::impl_ops(Add)]
+        fn add(self: Lanes, rhs: Lanes) -> Lanes {
+            let mut out = [0; 4];
+            for (i, lane) in out.iter_mut().enumerate() {
+                *lane = self.0[i].wrapping_add(rhs.0[i]);
+            }
+            Lanes(out)
+        }
+
+        let a = Lanes([1, 2, u32::MAX, 4]);
+        let b = Lanes([10, 20, 1, 40]);
+        let expected = Lanes([11, 22, 0, 44]);
+
+        assert_eq!(expected, &a + &b);
+        assert_eq!(expected, &a + Lanes([10, 20, 1, 40]));
+        assert_eq!(expected, Lanes([1, 2, u32::MAX, 4]) + &b);
+        assert_eq!(expected, a + b);
+    }
+
+
+    #[test]
+    fn test_impl_ops_zero_sized_operands() {
+        use std::marker::PhantomData;
+        use std::ops::Add;
+
+        #[derive(Debug, Default, PartialEq)]
+        struct Zero;
+
+        #[derive(Debug, Default, PartialEq)]
+        struct Succ<N>(PhantomData<N>);
+
+        #[opimps::impl_ops(Add)]
+        fn add(self: Zero, _rhs: Zero) -> Zero {
+            Zero
+        }
+
+        #[opimps::impl_ops(Add)]
+        fn add<N>(self: Zero, _rhs: Succ<N>) -> Succ<N> {
+            Succ(PhantomData)
+        }
+
+        #[opimps::impl_ops(Add)]
+        fn add<N>(self: Succ<N>, _rhs: Zero) -> Succ<N> {
+            Succ(PhantomData)
+        }
+
+        type Two = Succ<Succ<Zero>>;
+
+        assert_eq!(0, std::mem::size_of::<Two>());
+
+        let zero = Zero;
+        let two = Two::default();
+
+        assert_eq!(Zero, &zero + &zero);
+        assert_eq!(Two::default(), &zero + &two);
+        assert_eq!(Two::default(), Zero + &two);
+        assert_eq!(Two::default(), &two + Zero);
+        assert_eq!(Two::default(), two + zero);
+    }
+
+
+    #[test]
+    fn test_impl_ops_no_output() {
+        trait Overlaps<Rhs = Self> {
+            fn overlaps(self, rhs: Rhs) -> bool;
+        }
+
+        struct Span {
+            start: u32,
+            end: u32,
+        }
+
+        #[opimps::impl_ops(Overlaps; no_output)]
+        fn overlaps(self: Span, rhs: Span) -> bool {
+            self.start < rhs.end && rhs.start < self.end
+        }
+
+        #[opimps::impl_ops_rprim(Overlaps; no_output)]
+        fn overlaps(self: Span, rhs: u32) -> bool {
+            self.start <= rhs && rhs < self.end
+        }
+
+        let a = Span { start: 0, end: 10 };
+        let b = Span { start: 5, end: 15 };
+
+        assert!((&a).overlaps(&b));
+        assert!((&a).overlaps(Span { start: 9, end: 12 }));
+        assert!(!Span { start: 10, end: 12 }.overlaps(&a));
+        assert!((&a).overlaps(3));
+        assert!(!a.overlaps(10));
+        assert!(b.overlaps(Span { start: 0, end: 6 }));
+    }
+
+
+    #[test]
+    fn test_impl_scalar_ops() {
+        use std::ops::{Add, Mul};
+
+        #[derive(Debug, PartialEq)]
+        #[opimps::impl_scalar_ops(Add, wrapper = Secs, field = 0, prim = f64)]
+        #[opimps::impl_scalar_ops(Mul)]
+        struct Secs(f64);
+
+        #[derive(Debug, PartialEq)]
+        #[opimps::impl_scalar_ops(Add, field = count)]
+        struct Hits {
+            count: u32,
+        }
+
+        let a = Secs(1.5);
+
+        assert_eq!(Secs(2.5), Secs(1.5) + 1.0);
+        assert_eq!(Secs(2.5), &a + 1.0);
+        assert_eq!(Secs(2.5), 1.0 + Secs(1.5));
+        assert_eq!(Secs(2.5), 1.0 + &a);
+        assert_eq!(Secs(3.0), &a * 2.0);
+        assert_eq!(Secs(3.0), 2.0 * a);
+
+        assert_eq!(Hits { count: 3 }, Hits { count: 1 } + 2);
+        assert_eq!(Hits { count: 3 }, 2 + &Hits { count: 1 });
+    }
+
+
+    #[test]
+    fn test_impl_ops_idx() {
+        struct Grid<T> {
+            width: usize,
+            cells: Vec<T>,
+        }
+
+        #[opimps::impl_ops_idx]
+        fn index<T>(self: &Grid<T>, (x, y): (usize, usize)) -> &T {
+            &self.cells[y * self.width + x]
+        }
+
+        #[opimps::impl_ops_idx]
+        fn index_mut<T>(self: &mut Grid<T>, (x, y): (usize, usize)) -> &mut T {
+            &mut self.cells[y * self.width + x]
+        }
+
+        let mut grid = Grid { width: 2, cells: vec!['a', 'b', 'c', 'd'] };
+        assert_eq!('c', grid[(0, 1)]);
+
+        grid[(1, 1)] = 'z';
+        assert_eq!(vec!['a', 'b', 'c', 'z'], grid.cells);
+    }
+
+
+    #[test]
+    fn test_impl_deref() {
+        struct Stack<T>(Vec<T>);
+
+        #[opimps::impl_deref]
+        fn deref<T>(self: &Stack<T>) -> &[T] {
+            &self.0
+        }
+
+        #[opimps::impl_deref]
+        fn deref_mut<T>(self: &mut Stack<T>) -> &mut [T] {
+            &mut self.0
+        }
+
+        let mut stack = Stack(vec![3, 1, 2]);
+        stack.sort();
+
+        assert_eq!(&[1, 2, 3], &*stack);
+        assert_eq!(Some(&3), stack.last());
+    }
+
+
+    #[test]
+    #[allow(clippy::op_ref)] // Comparing borrowed operands is what's being tested.
+    fn test_impl_cmp_ops_partial_eq() {
+        struct Celsius<T>(T);
+        struct Kelvin(f64);
+
+        #[opimps::impl_cmp_ops(PartialEq)]
+        fn eq(self: Celsius<f64>, rhs: Kelvin) -> bool {
+            (self.0 + 273.15 - rhs.0).abs() < 1e-9
+        }
+
+        #[opimps::impl_cmp_ops(PartialEq)]
+        fn eq<T: PartialEq>(self: &Celsius<T>, rhs: &Celsius<T>) -> bool {
+            self.0 == rhs.0
+        }
+
+        let c = Celsius(20.0);
+        let k = Kelvin(293.15);
+
+        assert!(c == k);
+        assert!(c == &k);
+        assert!(&c == k);
+        assert!(&c == &k);
+        assert!(Celsius(0.0) != k);
+        assert!(&Celsius(0.0) != &k);
+
+        assert!(Celsius(1) == Celsius(1));
+        assert!(Celsius(1) == &Celsius(1));
+        assert!(&Celsius(1) == Celsius(1));
+        assert!(&Celsius(1) != &Celsius(2));
+    }
+
+
+    #[test]
+    #[allow(clippy::op_ref)] // Comparing borrowed operands is what's being tested.
+    fn test_impl_cmp_ops_partial_ord() {
+        use std::cmp::Ordering;
+
+        struct Meters(f64);
+        struct Feet(f64);
+
+        #[opimps::impl_cmp_ops(PartialEq)]
+        fn eq(self: Meters, rhs: Feet) -> bool {
+            self.0 == rhs.0 * 0.3048
+        }
+
+        #[opimps::impl_cmp_ops(PartialOrd)]
+        fn partial_cmp(self: &Meters, rhs: &Feet) -> Option<Ordering> {
+            self.0.partial_cmp(&(rhs.0 * 0.3048))
+        }
+
+        let m = Meters(1.0);
+        let f = Feet(3.0);
+
+        assert_eq!(Some(Ordering::Greater), m.partial_cmp(&f));
+        assert!(m > f);
+        assert!(m > &f);
+        assert!(&m > f);
+        assert!(&m > &f);
+        assert!(Meters(0.9144) <= Feet(3.0));
+        assert!(Meters(f64::NAN).partial_cmp(&f).is_none());
+    }
+
+
+    #[test]
+    fn test_impl_ops_prim_both_directions() {
+        use std::ops::{Add, Mul};
+
+        #[derive(Debug, PartialEq)]
+        struct Meters(f64);
+
+        #[opimps::impl_ops_prim(Mul)]
+        fn mul(self: f64, rhs: Meters) -> Meters {
+            Meters(self * rhs.0)
+        }
+
+        #[opimps::impl_ops_prim(Add)]
+        fn add(self: Meters, rhs: f64) -> Meters {
+            Meters(self.0 + rhs)
+        }
+
+        let m = Meters(1.5);
+
+        assert_eq!(Meters(3.0), 2.0 * Meters(1.5));
+        assert_eq!(Meters(3.0), 2.0 * &m);
+        assert_eq!(Meters(3.0), Meters(1.5) * 2.0);
+        assert_eq!(Meters(3.0), &m * 2.0);
+
+        assert_eq!(Meters(2.5), &m + 1.0);
+        assert_eq!(Meters(2.5), 1.0 + &m);
+        assert_eq!(Meters(2.5), 1.0 + m);
+    }
+
+
+    #[test]
+    fn test_impl_ops_assign_rprim() {
+        use std::ops::MulAssign;
+
+        #[derive(Debug, PartialEq)]
+        struct Vec3 {
+            x: f64,
+            y: f64,
+            z: f64,
+        }
+
+        #[opimps::impl_ops_assign_rprim(MulAssign)]
+        fn mul_assign(self: Vec3, rhs: f64) {
+            self.x *= rhs;
+            self.y *= rhs;
+            self.z *= rhs;
+        }
+
+        let mut v = Vec3 { x: 1.0, y: 2.0, z: 3.0 };
+        v *= 2.0;
+
+        assert_eq!(Vec3 { x: 2.0, y: 4.0, z: 6.0 }, v);
+    }
+
+
+    #[test]
+    fn test_impl_ops_from_assign() {
+        use std::ops::{Sub, SubAssign};
+
+        #[derive(Debug, Clone, PartialEq)]
+        struct Inventory {
+            items: Vec<&'static str>,
+        }
+
+        #[opimps::impl_ops_from_assign(Sub)]
+        fn sub_assign(self: Inventory, rhs: Inventory) {
+            self.items.retain(|item| !rhs.items.contains(item));
+        }
+
+        let stock = Inventory { items: vec!["nut", "bolt", "gear"] };
+        let sold = Inventory { items: vec!["bolt"] };
+        let expected = Inventory { items: vec!["nut", "gear"] };
+
+        assert_eq!(expected, &stock - &sold);
+        assert_eq!(expected, &stock - sold.clone());
+        assert_eq!(expected, stock.clone() - &sold);
+        assert_eq!(vec!["nut", "bolt", "gear"], stock.items);
+
+        let mut remaining = stock.clone();
+        remaining -= &sold;
+        assert_eq!(expected, remaining);
+        assert_eq!(expected, stock - sold);
+    }
+
+
+    #[test]
+    fn test_impl_assign_from_ops() {
+        use std::ops::{Mul, MulAssign};
+
+        #[derive(Debug, PartialEq)]
+        struct Poly(Vec<i32>);
+
+        #[opimps::impl_assign_from_ops(Mul)]
+        fn mul(self: Poly, rhs: Poly) -> Poly {
+            let mut out = vec![0; self.0.len() + rhs.0.len() - 1];
+            for (i, a) in self.0.iter().enumerate() {
+                for (j, b) in rhs.0.iter().enumerate() {
+                    out[i + j] += a * b;
+                }
+            }
+            Poly(out)
+        }
+
+        let mut p = Poly(vec![1, 1]);
+        let q = Poly(vec![1, -1]);
+
+        assert_eq!(Poly(vec![1, 0, -1]), &p * &q);
+
+        p *= &q;
+        assert_eq!(Poly(vec![1, 0, -1]), p);
+
+        p *= Poly(vec![2]);
+        assert_eq!(Poly(vec![2, 0, -2]), p);
+        assert_eq!(Poly(vec![2, -2, -2, 2]), p * q);
+    }
+
+
+    #[test]
+    fn test_method_name_inferred_from_trait() {
+        use std::ops::{Add, Neg, SubAssign};
+
+        #[derive(Debug, Clone, PartialEq)]
+        struct Interval(i32, i32);
+
+        #[opimps::impl_ops(Add)]
+        fn hull(self: Interval, rhs: Interval) -> Interval {
+            Interval(self.0.min(rhs.0), self.1.max(rhs.1))
+        }
+
+        #[opimps::impl_uni_ops(Neg)]
+        fn mirror(self: Interval) -> Interval {
+            Interval(-self.1, -self.0)
+        }
+
+        #[opimps::impl_op_assign(SubAssign)]
+        fn shift_left(self: Interval, rhs: i32) {
+            self.0 -= rhs;
+            self.1 -= rhs;
+        }
+
+        let a = Interval(0, 2);
+        let b = Interval(5, 7);
+
+        assert_eq!(Interval(0, 7), &a + &b);
+        assert_eq!(Interval(-2, 0), -&a);
+
+        let mut c = a.add(b);
+        c -= 3;
+        assert_eq!(Interval(-3, 4), c);
+    }
+
+
+    #[test]
+    fn test_omitted_output_is_self() {
+        use std::ops::{Mul, Sub};
+
+        #[derive(Debug, PartialEq)]
+        struct Celsius(f64);
+
+        #[opimps::impl_ops(Sub)]
+        fn sub(self: Celsius, rhs: Celsius) {
+            Celsius(self.0 - rhs.0)
+        }
+
+        #[opimps::impl_ops_rprim(Mul)]
+        fn mul(self: Celsius, rhs: f64) {
+            Celsius(self.0 * rhs)
+        }
+
+        let a = Celsius(21.5);
+        let b = Celsius(1.5);
+
+        assert_eq!(Celsius(20.0), &a - &b);
+        assert_eq!(Celsius(20.0), &a - b);
+        assert_eq!(Celsius(43.0), a * 2.0);
+    }
+
+
+    #[test]
+    fn test_impl_op_mutable_receivers() {
+        use std::ops::{Add, Shl};
+
+        #[derive(Debug, PartialEq)]
+        struct Buf(Vec<i32>);
+
+        // `(&mut buf) << item` pushes the item and returns the new length.
+        #[opimps::impl_op(Shl)]
+        fn shl(self: &mut Buf, rhs: i32) -> usize {
+            self.0.push(rhs);
+            self.0.len()
+        }
+
+        #[opimps::impl_op(Add)]
+        fn add(mut self: Buf, rhs: i32) -> Buf {
+            self.0.push(rhs);
+            self
+        }
+
+        let mut buf = Buf(vec![1]);
+
+        assert_eq!(2, &mut buf << 2);
+        assert_eq!(3, &mut buf << 3);
+        assert_eq!(Buf(vec![1, 2, 3, 4]), buf + 4);
+    }
+
+
+    #[test]
+    fn test_impl_ops_rhs_self() {
+        use std::ops::Mul;
+
+        #[derive(Debug, PartialEq)]
+        struct Matrix([[i32; 2]; 2]);
+
+        #[opimps::impl_ops(Mul)]
+        fn mul(self: Matrix, rhs: Self) -> Matrix {
+            let (a, b) = (self.0, rhs.0);
+            Matrix([
+                [a[0][0] * b[0][0] + a[0][1] * b[1][0], a[0][0] * b[0][1] + a[0][1] * b[1][1]],
+                [a[1][0] * b[0][0] + a[1][1] * b[1][0], a[1][0] * b[0][1] + a[1][1] * b[1][1]],
+            ])
+        }
+
+        let a = Matrix([[1, 1], [0, 1]]);
+        let b = Matrix([[1, 0], [1, 1]]);
+        let expected = Matrix([[2, 1], [1, 1]]);
+
+        assert_eq!(expected, &a * &b);
+        assert_eq!(expected, &a * Matrix([[1, 0], [1, 1]]));
+        assert_eq!(expected, Matrix([[1, 1], [0, 1]]) * &b);
+        assert_eq!(expected, a * b);
+    }
+
+
+    #[test]
+    fn test_const_generic_permutations() {
+        use std::ops::{Add, AddAssign, Mul, Neg};
+
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        struct Simd<const N: usize>([f32; N]);
+
+        #[opimps::impl_ops(Add)]
+        fn add<const N: usize>(self: Simd<N>, rhs: Simd<N>) -> Simd<N> {
+            Simd(std::array::from_fn(|i| self.0[i] + rhs.0[i]))
+        }
+
+        #[opimps::impl_ops_rprim(Mul)]
+        fn mul<const N: usize>(self: Simd<N>, rhs: f32) -> Simd<N> {
+            Simd(self.0.map(|x| x * rhs))
+        }
+
+        #[opimps::impl_ops_assign(AddAssign)]
+        fn add_assign<const N: usize>(self: Simd<N>, rhs: Simd<N>) {
+            for (x, y) in self.0.iter_mut().zip(rhs.0) {
+                *x += y;
+            }
+        }
+
+        #[opimps::impl_uni_ops(Neg)]
+        fn neg<const N: usize>(self: Simd<N>) -> Simd<N> {
+            Simd(self.0.map(|x| -x))
+        }
+
+        let a = Simd([1.0, 2.0, 3.0]);
+        let b = Simd([0.5, 0.5, 0.5]);
+
+        assert_eq!(Simd([1.5, 2.5, 3.5]), a.add(b));
+        assert_eq!(Simd([1.5, 2.5, 3.5]), (&a).add(b));
+        assert_eq!(Simd([1.5, 2.5, 3.5]), a.add(&b));
+        assert_eq!(Simd([1.5, 2.5, 3.5]), (&a).add(&b));
+        assert_eq!(Simd([2.0, 4.0, 6.0]), (&a).mul(2.0));
+        assert_eq!(Simd([-1.0, -2.0, -3.0]), -&a);
+
+        let mut c = Simd([1.0; 5]);
+        c += &Simd([1.0; 5]);
+        c += Simd([2.0; 5]);
+        assert_eq!(Simd([4.0; 5]), c);
+    }
+
+
+    #[test]
+    fn test_declared_references_are_not_borrowed_again() {
+        use std::ops::{Add, Mul, Sub};
+
+        #[derive(Debug, PartialEq)]
+        struct Names(Vec<String>);
+
+        #[opimps::impl_ops(Add)]
+        fn add(self: Names, rhs: &str) -> Names {
+            let mut names = self.0.clone();
+            names.push(rhs.to_string());
+            Names(names)
+        }
+
+        #[opimps::impl_ops(Sub)]
+        fn sub(self: &Names, rhs: Names) -> Names {
+            Names(self.0.iter().filter(|name| !rhs.0.contains(name)).cloned().collect())
+        }
+
+        #[opimps::impl_ops_rprim(Mul)]
+        fn mul(self: &Names, rhs: usize) -> usize {
+            self.0.len() * rhs
+        }
+
+        let names = Names(vec!["ann".to_string()]);
+        let more = &names + "bob";
+
+        assert_eq!(Names(vec!["ann".to_string(), "bob".to_string()]), more);
+        assert_eq!(more, Names(vec!["ann".to_string()]) + "bob");
+        assert_eq!(Names(vec!["bob".to_string()]), &more - &names);
+        assert_eq!(Names(vec!["bob".to_string()]), &more - names);
+        assert_eq!(4, &more * 2);
+    }
+
+
+    #[test]
+    fn test_higher_ranked_where_clause() {
+        use std::ops::{Add, AddAssign, Neg};
+
+        #[derive(Debug, Clone, PartialEq)]
+        struct Poly<T>(Vec<T>);
+
+        #[opimps::impl_ops(Add)]
+        fn add<T>(self: Poly<T>, rhs: Poly<T>) -> Poly<T> where for<'a> &'a T: Add<&'a T, Output = T> {
+            Poly(self.0.iter().zip(rhs.0.iter()).map(|(a, b)| a + b).collect())
+        }
+
+        #[opimps::impl_ops(Add; clone_refs)]
+        fn add<T: Clone>(self: Poly<T>, rhs: T) -> Poly<T> where for<'a> &'a T: Add<&'a T, Output = T> {
+            Poly(self.0.iter().map(|a| a + &rhs).collect())
+        }
+
+        #[opimps::impl_ops_assign(AddAssign)]
+        fn add_assign<T>(self: Poly<T>, rhs: Poly<T>) where for<'a> T: AddAssign<&'a T> {
+            for (a, b) in self.0.iter_mut().zip(rhs.0.iter()) {
+                *a += b;
+            }
+        }
+
+        #[opimps::impl_uni_ops(Neg)]
+        fn neg<T>(self: Poly<T>) -> Poly<T> where for<'a> &'a T: Neg<Output = T> {
+            Poly(self.0.iter().map(|a| -a).collect())
+        }
+
+        let a = Poly(vec![1, 2]);
+        let b = Poly(vec![10, 20]);
+
+        assert_eq!(Poly(vec![11, 22]), &a + &b);
+        assert_eq!(Poly(vec![11, 22]), &a + b.clone());
+        assert_eq!(Poly(vec![11, 22]), a.clone() + &b);
+        assert_eq!(Poly(vec![11, 22]), a.clone() + b.clone());
+        assert_eq!(Poly(vec![4, 5]), (&a).add(&3));
+        assert_eq!(Poly(vec![-1, -2]), -&a);
+
+        let mut c = a.clone();
+        c += &b;
+        c += b;
+        assert_eq!(Poly(vec![21, 42]), c);
+    }
+
+
+    #[test]
+    fn test_defaulted_generics() {
+        use std::ops::{Add, SubAssign};
+
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        struct Num<T = f64>(T);
+
+        #[opimps::impl_ops(Add)]
+        fn add<T: Add<Output = T> + Copy = f64>(self: Num<T>, rhs: Num<T>) -> Num<T> {
+            Num(self.0 + rhs.0)
+        }
+
+        #[opimps::impl_op_assign(SubAssign)]
+        fn sub_assign<T: SubAssign = f64>(self: Num<T>, rhs: T) {
+            self.0 -= rhs;
+        }
+
+        let a: Num = Num(1.5);
+
+        assert_eq!(Num(3.0), a.add(a));
+        assert_eq!(Num(3), Num(1).add(&Num(2)));
+
+        let mut b = a;
+        b -= 0.5;
+        assert_eq!(Num(1.0), b);
+    }
+
+
+    #[test]
+    fn test_impl_ops_only() {
+        use std::ops::Add;
+
+        #[derive(Debug, Clone, PartialEq)]
+        struct Tally(u32);
+
+        // Implemented by hand, so it mustn't be generated.
+        impl Add<&Tally> for Tally {
+            type Output = Tally;
+
+            fn add(self, rhs: &Tally) -> Tally {
+                Tally(self.0 + rhs.0 + 100)
+            }
+        }
+
+        #[opimps::impl_ops(Add, only(owned_owned, ref_owned, ref_ref))]
+        fn add(self: Tally, rhs: Tally) -> Tally {
+            Tally(self.0 + rhs.0)
+        }
+
+        let a = Tally(1);
+        let b = Tally(2);
+
+        assert_eq!(Tally(3), &a + &b);
+        assert_eq!(Tally(3), &a + b.clone());
+        assert_eq!(Tally(103), a.clone() + &b);
+        assert_eq!(Tally(3), a + b);
+    }
+
+
+    #[test]
+    fn test_impl_ops_skip() {
+        use std::ops::Mul;
+
+        #[derive(Debug, Clone, PartialEq)]
+        struct Scale(i32);
+
+        impl Mul for Scale {
+            type Output = Scale;
+
+            fn mul(self, rhs: Scale) -> Scale {
+                Scale(-(self.0 * rhs.0))
+            }
+        }
+
+        #[opimps::impl_ops(Mul; skip(owned_owned))]
+        fn mul(self: Scale, rhs: Scale) -> Scale {
+            Scale(self.0 * rhs.0)
+        }
+
+        let a = Scale(2);
+        let b = Scale(3);
+
+        assert_eq!(Scale(6), &a * &b);
+        assert_eq!(Scale(6), a.clone() * &b);
+        assert_eq!(Scale(6), &a * b.clone());
+        assert_eq!(Scale(-6), a * b);
+    }
+
+
+    #[test]
+    fn test_impl_ops_commutative() {
+        use std::ops::Mul;
+
+        #[derive(Debug, PartialEq)]
+        struct Speed(f64);
+
+        #[derive(Debug, PartialEq)]
+        struct Hours(f64);
+
+        #[derive(Debug, PartialEq)]
+        struct Distance(f64);
+
+        #[opimps::impl_ops(Mul; commutative)]
+        fn mul(self: Speed, rhs: Hours) -> Distance {
+            Distance(self.0 * rhs.0)
+        }
+
+        let speed = Speed(60.0);
+        let hours = Hours(1.5);
+
+        assert_eq!(Distance(90.0), &speed * &hours);
+        assert_eq!(Distance(90.0), &hours * &speed);
+        assert_eq!(Distance(90.0), &hours * Speed(60.0));
+        assert_eq!(Distance(90.0), Hours(1.5) * &speed);
+        assert_eq!(Distance(90.0), &speed * Hours(1.5));
+        assert_eq!(Distance(90.0), Speed(60.0) * &hours);
+        assert_eq!(Distance(90.0), hours * speed);
+        assert_eq!(Distance(90.0), Speed(60.0) * Hours(1.5));
+    }
+
+
+    #[test]
+    fn test_impl_ops_rprim_type_list() {
+        use std::ops::Shl;
+
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        struct Bits(u64);
+
+        #[opimps::impl_ops_rprim(Shl; u8, u16, u32, u64, usize; inline_refs)]
+        fn shl(self: Bits, rhs: u8) -> Bits {
+            Bits(self.0 << rhs)
+        }
+
+        let bits = Bits(1);
+
+        assert_eq!(Bits(2), bits << 1u8);
+        assert_eq!(Bits(4), bits << 2u16);
+        assert_eq!(Bits(8), &bits << 3u32);
+        assert_eq!(Bits(16), bits << 4u64);
+        assert_eq!(Bits(32), &bits << 5usize);
+    }
+
+    #[test]
+    fn test_impl_ops_rhs_types() {
+        use std::ops::Mul;
+
+        #[derive(Debug, PartialEq)]
+        struct Meters(f64);
+
+        #[derive(Debug, PartialEq)]
+        struct Ratio(f64);
+
+        /// Scales the distance.
+        #[opimps::impl_ops(Mul, rhs_types(Ratio, Meters))]
+        fn mul(self: Meters, rhs: Ratio) -> Meters {
+            Meters(self.0 * rhs.0)
+        }
+
+        let meters = Meters(2.0);
+
+        assert_eq!(Meters(3.0), &meters * &Ratio(1.5));
+        assert_eq!(Meters(4.0), &meters * Meters(2.0));
+        assert_eq!(Meters(4.0), Meters(2.0) * &meters);
+        assert_eq!(Meters(3.0), meters * Ratio(1.5));
+    }
+
+    #[test]
+    fn test_impl_ops_more_traits() {
+        use std::ops::{Add, BitOr};
+
+        #[derive(Debug, PartialEq)]
+        struct Flags(u8);
+
+        #[opimps::impl_ops(BitOr, Add)]
+        fn union(self: Flags, rhs: Flags) -> Flags {
+            Flags(self.0.bitor(rhs.0))
+        }
+
+        assert_eq!(Flags(0b11), Flags(0b01) | Flags(0b10));
+        assert_eq!(Flags(0b11), &Flags(0b01) | &Flags(0b10));
+        assert_eq!(Flags(0b11), Flags(0b01) + &Flags(0b10));
+        assert_eq!(Flags(0b11), &Flags(0b01) + Flags(0b10));
+    }
+
+    #[test]
+    fn test_impl_iter_sum_generic() {
+        use std::ops::AddAssign;
+
+        #[derive(Debug, Clone, PartialEq)]
+        struct Total<T>(T);
+
+        #[opimps::impl_iter_sum]
+        fn sum<T, I>(iter: I) -> Total<T>
+        where
+            T: Default + AddAssign,
+            I: Iterator<Item = Total<T>>
+        {
+            let mut total = T::default();
+
+            for item in iter {
+                total += item.0;
+            }
+
+            Total(total)
+        }
+
+        let totals = vec![Total(1), Total(2), Total(3)];
+
+        assert_eq!(Total(6), totals.iter().sum::<Total<i32>>());
+        assert_eq!(Total(6), totals.into_iter().sum::<Total<i32>>());
+    }
+
+    #[test]
+    fn test_impl_from() {
+        #[derive(Debug, PartialEq)]
+        struct Name(String);
+
+        #[derive(Debug, PartialEq)]
+        struct Meters(f64);
+
+        #[derive(Debug, PartialEq)]
+        struct Feet<T>(T);
+
+        #[opimps::impl_from]
+        fn from(value: &str) -> Name {
+            Name(value.to_owned())
+        }
+
+        #[opimps::impl_from]
+        fn from<T: Into<f64>>(value: Feet<T>) -> Meters {
+            Meters(value.0.into() * 0.3)
+        }
+
+        assert_eq!(Name(String::from("Ada")), Name::from("Ada"));
+        assert_eq!(Meters(3.0), Meters::from(Feet(10u8)));
+        assert_eq!(Meters(3.0), Feet(10.0f32).into());
+    }
+
+    #[test]
+    fn test_impl_try_from() {
+        #[derive(Debug, PartialEq)]
+        struct Even(u64);
+
+        #[opimps::impl_try_from]
+        fn try_from(value: i32) -> Result<Even, String> {
+            match u64::try_from(value) {
+                Ok(value) if value % 2 == 0 => Ok(Even(value)),
+                _ => Err(format!("{} isn't even", value))
+            }
+        }
+
+        assert_eq!(Ok(Even(4)), Even::try_from(4));
+        assert_eq!(Err(String::from("3 isn't even")), Even::try_from(3));
+
+        let even: Result<Even, _> = 8.try_into();
+        assert_eq!(Ok(Even(8)), even);
+    }
+
+    #[test]
+    fn test_output_tied_to_receiver_lifetime() {
+        use std::collections::HashMap;
+
+        trait Lookup<K> {
+            type Output;
+            fn lookup(self, key: K) -> Self::Output;
+        }
+
+        struct Table(HashMap<String, String>);
+
+        #[opimps::impl_ops(Lookup)]
+        fn lookup<'a>(self: &'a Table, rhs: String) -> &'a str { &self.0[rhs.as_str()] }
+
+        #[opimps::impl_op(Lookup)]
+        fn lookup<'a>(self: &'a Table, rhs: &'a str) -> Option<&'a String> {
+            self.0.iter().find(|(_, value)| *value == rhs).map(|(key, _)| key)
+        }
+
+        let table = Table(HashMap::from([(String::from("one"), String::from("uno"))]));
+        let one = String::from("one");
+
+        assert_eq!("uno", (&table).lookup(one.clone()));
+        assert_eq!("uno", (&table).lookup(&one));
+        assert_eq!(Some(&one), (&table).lookup("uno"));
+    }
+
+    #[test]
+    fn test_cfg_gates_the_impl() {
+        use std::ops::{Add, AddAssign};
+
+        #[derive(Debug, PartialEq)]
+        struct Cells(u32);
+
+        #[opimps::impl_op(Add)]
+        #[cfg(any())]
+        fn add(self: Cells, rhs: Cells) -> Cells {
+            unreachable!()
+        }
+
+        #[opimps::impl_op(Add)]
+        #[cfg(not(any()))]
+        fn add(self: Cells, rhs: Cells) -> Cells {
+            Cells(self.0 + rhs.0)
+        }
+
+        #[opimps::impl_op_assign(AddAssign)]
+        #[cfg(any())]
+        fn add_assign(self: Cells, rhs: Cells) {
+            unreachable!()
+        }
+
+        #[opimps::impl_op_assign(AddAssign)]
+        #[cfg(not(any()))]
+        fn add_assign(self: Cells, rhs: Cells) {
+            self.0 += rhs.0;
+        }
+
+        let mut cells = Cells(1) + Cells(2);
+        cells += Cells(3);
+
+        assert_eq!(Cells(6), cells);
+    }
+
+    #[test]
+    fn test_impl_uni_ops_mutable_receiver() {
+        trait Take {
+            type Output;
+            fn take(self) -> Self::Output;
+        }
+
+        #[derive(Debug, Clone, PartialEq)]
+        struct Stack(Vec<u8>);
+
+        #[opimps::impl_uni_ops(Take)]
+        fn take(self: &mut Stack) -> Option<u8> {
+            self.0.pop()
+        }
+
+        #[opimps::impl_uni_ops(Take)]
+        fn take(self: Stack) -> Vec<u8> {
+            self.0.clone()
+        }
+
+        let mut stack = Stack(vec![1, 2]);
+
+        assert_eq!(Some(2), (&mut stack).take());
+        assert_eq!(vec![1], (&stack).take());
+        assert_eq!(vec![1], stack.take());
+    }
+
+    #[test]
+    fn test_impl_ops_assign_through_interior_reference() {
+        use std::ops::AddAssign;
+
+        struct Counter<'a>(&'a mut i32);
+
+        #[opimps::impl_ops_assign(AddAssign)]
+        fn add_assign<'a>(self: Counter<'a>, rhs: i32) {
+            *self.0 += rhs;
+        }
+
+        let mut count = 1;
+        let mut counter = Counter(&mut count);
+
+        counter += 2;
+        counter += &3;
+
+        assert_eq!(6, count);
+    }
+
+    #[test]
+    fn test_body_locals_keep_their_hygiene() {
+        use std::ops::Add;
+
+        #[derive(Debug, Clone, PartialEq)]
+        struct Pair<T>(T, T);
+
+        #[opimps::impl_ops(Add)]
+        fn add<T: Add<Output = T> + Clone>(self: Pair<T>, rhs: Pair<T>) -> Pair<T> {
+            let self_ = self;
+            let lhs = {
+                let self_ = self_.clone();
+                let value = rhs;
+                (self_, value)
+            };
+
+            Pair(lhs.0.0.clone() + lhs.1.0.clone(), lhs.0.1.clone() + lhs.1.1.clone())
+        }
+
+        let (a, b) = (Pair(1, 2), Pair(10, 20));
+
+        assert_eq!(Pair(11, 22), a.clone() + b.clone());
+        assert_eq!(Pair(11, 22), a.clone() + &b);
+        assert_eq!(Pair(11, 22), &a + b.clone());
+        assert_eq!(Pair(11, 22), &a + &b);
+    }
+
+    #[test]
+    fn test_impl_ops_block() {
+        use std::cmp::Ordering;
+
+        struct Score<T>(T);
+
+        #[opimps::impl_ops_block]
+        impl<T: Into<i64> + Copy> PartialEq<i64> for Score<T> {
+            fn eq(&self, rhs: &i64) -> bool {
+                self.0.into() == *rhs
+            }
+        }
+
+        #[opimps::impl_ops_block]
+        impl<T: Into<i64> + Copy> PartialOrd<i64> for Score<T> {
+            fn partial_cmp(&self, rhs: &i64) -> Option<Ordering> {
+                self.0.into().partial_cmp(rhs)
+            }
+
+            fn gt(&self, rhs: &i64) -> bool {
+                self.0.into() > *rhs
+            }
+        }
+
+        let score = Score(7u8);
+
+        assert!(score == 7);
+        assert!(&score == 7);
+        assert!(PartialEq::<&i64>::eq(&score, &&7));
+        assert!(score > 3);
+        assert!(&score > 3);
+        assert!(!PartialOrd::<&i64>::lt(&score, &&3));
+        assert_eq!(Some(Ordering::Less), PartialOrd::<i64>::partial_cmp(&&score, &9));
+    }
+
+    mod math {
+        use std::ops::{Add, Mul};
+
+        #[derive(Debug, Clone, PartialEq)]
+        pub struct Matrix<const R: usize, const C: usize>(pub [[i32; C]; R]);
+
+        pub type Mat4 = Matrix<4, 4>;
+
+        #[opimps::impl_ops(Mul)]
+        fn mul(self: Mat4, rhs: Mat4) -> Mat4 {
+            Matrix(std::array::from_fn(|i| std::array::from_fn(|j| (0..4).map(|k| self.0[i][k] * rhs.0[k][j]).sum())))
+        }
+
+        #[opimps::impl_ops(Add)]
+        fn add(self: crate::tests::math::Matrix<2, 2>, rhs: self::Matrix<2, 2>) -> Matrix<2, 2> {
+            Matrix(std::array::from_fn(|i| std::array::from_fn(|j| self.0[i][j] + rhs.0[i][j])))
+        }
+    }
+
+    #[test]
+    fn test_alias_and_qualified_self_types() {
+        use math::{Mat4, Matrix};
+
+        let identity: Mat4 = Matrix(std::array::from_fn(|i| std::array::from_fn(|j| (i == j) as i32)));
+        let scaled: Mat4 = Matrix([[2; 4]; 4]);
+
+        assert_eq!(scaled, &identity * &scaled);
+        assert_eq!(scaled, identity.clone() * scaled.clone());
+
+        let ones = Matrix([[1, 1], [1, 1]]);
+
+        assert_eq!(Matrix([[2, 2], [2, 2]]), &ones + ones.clone());
+    }
+
+    #[test]
+    fn test_impl_ops_bare_generic_rhs() {
+        use std::ops::Add;
+
+        #[derive(Debug, PartialEq)]
+        struct Wrapper<T>(T);
+
+        #[opimps::impl_ops(Add)]
+        fn add<T: Add<Output = T> + Copy>(self: Wrapper<T>, rhs: T) -> Wrapper<T> {
+            Wrapper(self.0 + rhs)
+        }
+
+        let (wrapper, two) = (Wrapper(1.5), 2.0);
+
+        assert_eq!(Wrapper(3.5), Wrapper(1.5) + 2.0);
+        assert_eq!(Wrapper(3.5), Wrapper(1.5).add(&two));
+        assert_eq!(Wrapper(3.5), &wrapper + 2.0);
+        assert_eq!(Wrapper(3.5), (&wrapper).add(&two));
+    }
+
+    #[test]
+    fn test_impl_neg_and_not() {
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        struct Mask(u8);
+
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        struct Delta<T>(T);
+
+        #[opimps::impl_not]
+        fn invert(self: Mask) -> Mask {
+            Mask(!self.0)
+        }
+
+        #[opimps::impl_neg(strict)]
+        fn neg<T: std::ops::Neg<Output = T> + Copy>(self: Delta<T>) -> Delta<T> {
+            Delta(-self.0)
+        }
+
+        let mask = Mask(0b1111_0000);
+
+        assert_eq!(Mask(0b0000_1111), !mask);
+        assert_eq!(Mask(0b0000_1111), !&mask);
+        assert_eq!(Delta(-2), -Delta(2));
+        assert_eq!(Delta(-2.5), -&Delta(2.5));
+    }
+
+    #[test]
+    fn test_associated_type_projection_in_output() {
+        use std::ops::Add;
+
+        #[derive(Debug, PartialEq)]
+        struct W<T>(T);
+
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        struct Cents(u32);
+
+        #[derive(Debug, PartialEq)]
+        struct Total(u64);
+
+        impl Add for Cents {
+            type Output = Total;
+
+            fn add(self, rhs: Cents) -> Total {
+                Total(self.0 as u64 + rhs.0 as u64)
+            }
+        }
+
+        #[opimps::impl_ops(Add)]
+        fn add<T: Add + Copy>(self: W<T>, rhs: W<T>) -> W<T::Output> {
+            W(self.0 + rhs.0)
+        }
+
+        let (a, b) = (W(Cents(150)), W(Cents(250)));
+
+        assert_eq!(W(Total(400)), &a + &b);
+        assert_eq!(W(Total(400)), &a + W(Cents(250)));
+        assert_eq!(W(Total(400)), W(Cents(150)) + &b);
+        assert_eq!(W(Total(400)), a + b);
+        assert_eq!(W(3), W(1) + W(2));
+    }
+
+    #[test]
+    fn test_unsafe_fn_body() {
+        use std::ops::Mul;
+
+        #[derive(Debug, PartialEq)]
+        struct Raw(*const u32);
+
+        /// Reads both pointers, which must be valid.
+        #[opimps::impl_ops(Mul)]
+        unsafe fn mul(self: Raw, rhs: Raw) -> u32 {
+            *self.0 * *rhs.0
+        }
+
+        let (x, y) = (6, 7);
+        let (a, b) = (Raw(&x), Raw(&y));
+
+        assert_eq!(42, &a * &b);
+        assert_eq!(42, a * b);
+    }
+
+    #[test]
+    fn test_output_reference_borrows_from_receiver() {
+        trait Pick<Rhs> {
+            type Output;
+            fn pick(self, rhs: Rhs) -> Self::Output;
+        }
+
+        struct Words(Vec<String>);
+
+        #[opimps::impl_op(Pick)]
+        fn pick(self: &Words, rhs: usize) -> &String {
+            &self.0[rhs]
+        }
+
+        #[opimps::impl_ops(Pick)]
+        fn pick(self: &Words, rhs: char) -> &str {
+            self.0.iter().find(|word| word.starts_with(*rhs)).map_or("", |word| word.as_str())
+        }
+
+        let words = Words(vec![String::from("one"), String::from("two")]);
+
+        assert_eq!("two", (&words).pick(1));
+        assert_eq!("one", (&words).pick('o'));
+        assert_eq!("two", (&words).pick(&'t'));
+    }
+
+    #[test]
+    fn test_lifetime_only_in_rhs() {
+        use std::ops::Add;
+
+        #[derive(Debug, PartialEq)]
+        struct Num(i32);
+
+        struct Ref<'a>(&'a i32);
+
+        #[opimps::impl_ops(Add)]
+        fn add<'a>(self: Num, rhs: Ref<'a>) -> Num {
+            Num(self.0 + *rhs.0)
+        }
+
+        let (a, b) = (Num(2), 3);
+        let rhs = Ref(&b);
+
+        assert_eq!(Num(5), &a + &rhs);
+        assert_eq!(Num(5), &a + Ref(&b));
+        assert_eq!(Num(5), Num(2) + &rhs);
+        assert_eq!(Num(5), a + rhs);
+    }
+
+    #[test]
+    fn test_impl_ops_with_mut_lhs() {
+        use std::ops::Add;
+
+        #[derive(Debug, Clone, PartialEq)]
+        struct Path(Vec<String>);
+
+        #[opimps::impl_ops(Add, with_mut_lhs)]
+        fn add(self: Path, rhs: Path) -> Path {
+            Path(self.0.iter().chain(rhs.0.iter()).cloned().collect())
+        }
+
+        let mut a = Path(vec![String::from("usr")]);
+        let b = Path(vec![String::from("bin")]);
+        let joined = Path(vec![String::from("usr"), String::from("bin")]);
+
+        let builder = &mut a;
+        assert_eq!(joined, builder.add(&b));
+        assert_eq!(joined, builder.add(b.clone()));
+        assert_eq!(joined, (&a).add(&b));
+        assert_eq!(joined, a.clone().add(b.clone()));
+        assert_eq!(joined, &a + b);
+    }
+
+    #[test]
+    fn test_where_self_bound() {
+        use std::ops::Add;
+
+        #[derive(Debug, Clone, PartialEq)]
+        struct W<T>(T);
+
+        #[opimps::impl_ops(Add)]
+        fn add<T>(self: W<T>, rhs: W<T>) -> W<T> where Self: Clone, T: Add<Output = T> {
+            let (lhs, rhs) = (self.clone(), rhs.clone());
+            W(lhs.0 + rhs.0)
+        }
+
+        #[opimps::impl_op(Add)]
+        fn add(self: W<String>, rhs: &str) -> W<String> where Self: Clone {
+            W(self.0.clone() + rhs)
+        }
+
+        let (a, b) = (W(2), W(3));
+
+        assert_eq!(W(5), &a + &b);
+        assert_eq!(W(5), &a + W(3));
+        assert_eq!(W(5), W(2) + &b);
+        assert_eq!(W(5), a + b);
+        assert_eq!(W(String::from("ab")), W(String::from("a")) + "b");
+    }
+
+    #[test]
+    fn test_output_type_name() {
+        trait Join<Rhs = Self> {
+            type Result;
+            fn join(self, rhs: Rhs) -> Self::Result;
+        }
+
+        trait Flip {
+            type Result;
+            fn flip(self) -> Self::Result;
+        }
+
+        #[derive(Debug, PartialEq)]
+        struct Pair(i32, i32);
+
+        #[opimps::impl_op(Join, output = Result)]
+        fn join(self: Pair, rhs: i32) -> Vec<i32> {
+            vec![self.0, self.1, rhs]
+        }
+
+        #[opimps::impl_ops(Join; output = Result)]
+        fn join(self: Pair, rhs: Pair) -> Pair {
+            Pair(self.0 + rhs.0, self.1 + rhs.1)
+        }
+
+        #[opimps::impl_uni_ops(Flip; output = Result)]
+        fn flip(self: Pair) -> Pair {
+            Pair(self.1, self.0)
+        }
+
+        assert_eq!(vec![1, 2, 3], Pair(1, 2).join(3));
+        assert_eq!(Pair(4, 6), (&Pair(1, 2)).join(&Pair(3, 4)));
+        assert_eq!(Pair(4, 6), Pair(1, 2).join(Pair(3, 4)));
+        assert_eq!(Pair(2, 1), (&Pair(1, 2)).flip());
+        assert_eq!(Pair(2, 1), Pair(1, 2).flip());
+    }
+
+    #[test]
+    fn test_trait_path_with_generic_arguments() {
+        use std::ops::AddAssign;
+
+        trait Rotate<const N: u32> {
+            type Output;
+            fn rotate(self) -> Self::Output;
+        }
+
+        trait Mix<Lhs, Rhs> {
+            type Output;
+            fn mix(self, rhs: Rhs) -> Self::Output;
+        }
+
+        #[derive(Debug, PartialEq)]
+        struct Bits(u8);
+
+        #[opimps::impl_uni_op(Rotate<4>)]
+        fn rotate(self: Bits) -> Bits {
+            Bits(self.0.rotate_left(4))
+        }
+
+        #[opimps::impl_op(Mix<u8, Bits>)]
+        fn mix(self: Bits, rhs: Bits) -> Bits {
+            Bits(self.0 ^ rhs.0)
+        }
+
+        #[opimps::impl_op_assign(AddAssign<u8>)]
+        fn add_assign(self: Bits, rhs: u8) {
+            self.0 += rhs;
+        }
+
+        let mut bits = Bits(0b0000_0011);
+        bits += 1;
+
+        assert_eq!(Bits(0b0100_0000), bits.rotate());
+        assert_eq!(Bits(0b0101), Bits(0b0110).mix(Bits(0b0011)));
+    }
+
+    #[test]
+    fn test_operand_forms_without_overlap() {
+        use std::ops::{Add, Div, Mul, Sub};
+
+        #[derive(Debug, Clone, PartialEq)]
+        struct Meters(f64);
+
+        // Same types: `Meters + Meters` with `&Meters` on either side, 4 impls.
+        #[opimps::impl_ops(Add)]
+        fn add(self: Meters, rhs: Meters) -> Meters { Meters(self.0 + rhs.0) }
+
+        // A declared `&str` is only borrowed: `Meters - &str` and `&Meters - &str`, 2 impls.
+        #[opimps::impl_ops(Sub)]
+        fn sub(self: Meters, rhs: &str) -> Meters { Meters(self.0 - rhs.len() as f64) }
+
+        // A declared `&Meters` is only borrowed: `&Meters * f64` and `&Meters * &f64`, 2 impls.
+        #[opimps::impl_ops(Mul)]
+        fn mul(self: &Meters, rhs: f64) -> Meters { Meters(self.0 * rhs) }
+
+        // A free type parameter already covers the borrowed `rhs`: `Meters / T` and `&Meters / T`, 2 impls.
+        #[opimps::impl_ops(Div)]
+        fn div<T: Into<f64>>(self: Meters, rhs: T) -> Meters { Meters(self.0 / rhs.into()) }
+
+        let m = Meters(6.0);
+
+        assert_eq!(Meters(12.0), &m + &m);
+        assert_eq!(Meters(3.0), &m - "abc");
+        assert_eq!(Meters(12.0), (&m).mul(&2.0));
+        assert_eq!(Meters(3.0), &m / 2.0f32);
+        assert_eq!(Meters(3.0), m / 2u8);
+    }
+
+    #[test]
+    fn test_same_type_operands_need_neither_copy_nor_clone() {
+        use std::ops::Mul;
+
+        // Neither `Copy` nor `Clone`, so no permutation can rely on either.
+        struct Vec3 {
+            x: f32,
+            y: f32,
+            z: f32,
+            label: String
+        }
+
+        #[opimps::impl_ops(Mul)]
+        fn dot(self: Vec3, rhs: Vec3) -> f32 {
+            self.x * rhs.x + self.y * rhs.y + self.z * rhs.z
+        }
+
+        let a = Vec3 { x: 1.0, y: 2.0, z: 3.0, label: String::from("a") };
+        let b = Vec3 { x: 4.0, y: 5.0, z: 6.0, label: String::from("b") };
+
+        assert_eq!(32.0, &a * &b);
+        assert_eq!(32.0, &a * Vec3 { x: 4.0, y: 5.0, z: 6.0, label: String::new() });
+        assert_eq!(32.0, Vec3 { x: 1.0, y: 2.0, z: 3.0, label: String::new() } * &b);
+        assert_eq!("ab", a.label.clone() + &b.label);
+        assert_eq!(32.0, a * b);
+    }
+
+    #[test]
+    fn test_impl_shl_shr_ops() {
+        use std::ops::{Shl, Shr};
+
+        #[derive(Debug, PartialEq)]
+        struct Bits(u16);
+
+        #[opimps::impl_shl_shr_ops]
+        mod shifts {
+            /// Shifts the bits towards the most significant one.
+            fn shl(self: Bits, rhs: u32) -> Bits {
+                Bits(self.0 << rhs)
+            }
+
+            fn shr(self: Bits, rhs: u32) -> Bits {
+                Bits(self.0 >> rhs)
+            }
+        }
+
+        let bits = Bits(0b0110);
+
+        assert_eq!(Bits(0b1100), &bits << 1);
+        assert_eq!(Bits(0b1100), (&bits).shl(&1));
+        assert_eq!(Bits(0b0011), &bits >> 1);
+        assert_eq!(Bits(0b0011), (&bits).shr(&1));
+        assert_eq!(Bits(0b11000), bits << 2);
+    }
+
+    #[test]
+    fn test_trailing_comma_in_attribute() {
+        use std::ops::Add;
+
+        #[derive(Debug)]
+        struct Score(u32);
+
+        #[opimps::impl_ops(Add,)]
+        fn add(self: Score, rhs: Score) -> Score { Score(self.0 + rhs.0) }
+
+        #[opimps::impl_cmp_ops(PartialEq,)]
+        fn eq(self: Score, rhs: Score) -> bool { self.0 == rhs.0 }
+
+        assert_eq!(Score(3), &Score(1) + Score(2));
+    }
+
+    #[test]
+    fn test_impl_ops_on_enum() {
+        use std::ops::Add;
+
+        #[derive(Debug, PartialEq)]
+        enum Money {
+            Usd(u64),
+            Eur(u64),
+            Mixed { usd: u64, eur: u64 }
+        }
+
+        #[opimps::impl_ops(Add)]
+        fn add(self: Money, rhs: Money) -> Money {
+            match (self, rhs) {
+                (Money::Usd(a), Money::Usd(b)) => Money::Usd(a + b),
+                (Money::Eur(a), Money::Eur(b)) => Money::Eur(a + b),
+                (Money::Usd(usd), Money::Eur(eur)) | (Money::Eur(eur), Money::Usd(usd)) => Money::Mixed { usd: *usd, eur: *eur },
+                (Money::Mixed { usd, eur }, _) | (_, Money::Mixed { usd, eur }) => Money::Mixed { usd: *usd, eur: *eur }
+            }
+        }
+
+        let (usd, eur) = (Money::Usd(3), Money::Eur(4));
+
+        assert_eq!(Money::Usd(6), &usd + &usd);
+        assert_eq!(Money::Mixed { usd: 3, eur: 4 }, &usd + &eur);
+        assert_eq!(Money::Mixed { usd: 3, eur: 4 }, &eur + Money::Usd(3));
+        assert_eq!(Money::Eur(8), Money::Eur(4) + &eur);
+        assert_eq!(Money::Mixed { usd: 3, eur: 4 }, usd + eur);
+    }
+
+    #[test]
+    fn test_boxed_operands() {
+        use std::ops::{Add, Mul, Neg};
+
+        #[derive(Debug, PartialEq)]
+        enum Node {
+            Leaf(i32),
+            Sum(Box<Node>, Box<Node>),
+            Scaled(Box<Node>, i32)
+        }
+
+        // Boxes are only taken by value: `Box<Node> + Box<Node>`, 1 impl.
+        #[opimps::impl_ops(Add)]
+        fn add(self: Box<Node>, rhs: Box<Node>) -> Box<Node> {
+            Box::new(Node::Sum(self, rhs))
+        }
+
+        // `Box<Node> * i32` and `Box<Node> * &i32`, 2 impls.
+        #[opimps::impl_ops(Mul)]
+        fn mul(self: Box<Node>, rhs: i32) -> Box<Node> {
+            Box::new(Node::Scaled(self, *rhs))
+        }
+
+        #[opimps::impl_uni_ops(Neg)]
+        fn neg(self: Box<Node>) -> Box<Node> {
+            Box::new(Node::Scaled(self, -1))
+        }
+
+        let leaf = |value| Box::new(Node::Leaf(value));
+
+        assert_eq!(Box::new(Node::Sum(leaf(1), leaf(2))), leaf(1) + leaf(2));
+        assert_eq!(Box::new(Node::Scaled(leaf(1), 3)), leaf(1) * 3);
+        assert_eq!(Box::new(Node::Scaled(leaf(1), 3)), leaf(1).mul(&3));
+        assert_eq!(Box::new(Node::Scaled(leaf(1), -1)), -leaf(1));
+    }
+
+    #[test]
+    fn test_self_in_the_output() {
+        use std::ops::{Mul, Neg, Sub};
+
+        #[derive(Debug, PartialEq)]
+        struct Point(i32, i32);
+
+        #[opimps::impl_ops(Sub)]
+        fn sub(self: Point, rhs: Point) -> Option<Self> {
+            Some(Point(self.0.checked_sub(rhs.0)?, self.1.checked_sub(rhs.1)?))
+        }
+
+        #[opimps::impl_ops_prim(Mul)]
+        fn mul(self: Point, rhs: i32) -> Self {
+            Point(self.0 * rhs, self.1 * rhs)
+        }
+
+        #[opimps::impl_uni_ops(Neg)]
+        fn neg(self: Point) -> Self {
+            Point(-self.0, -self.1)
+        }
+
+        let p = Point(3, 4);
+
+        assert_eq!(Some(Point(0, 0)), &p - &p);
+        assert_eq!(Some(Point(2, 3)), Point(3, 4) - Point(1, 1));
+        assert_eq!(Point(6, 8), &p * 2);
+        assert_eq!(Point(6, 8), 2 * &p);
+        assert_eq!(Point(-3, -4), -&p);
+        assert_eq!(Point(-3, -4), -p);
+    }
+
+    #[test]
+    fn test_multi_line_where_clause() {
+        use std::ops::{Add, Mul, Sub};
+
+        #[derive(Debug, PartialEq)]
+        struct Lerp<T>(T, T);
+
+        #[opimps::impl_ops(Mul)]
+        fn mul<T, U>(self: Lerp<T>, rhs: U) -> T
+        where
+            // The endpoints are combined by value.
+            T: Add<Output = T>
+                + Sub<Output = T>
+                + Mul<Output = T>
+                + Copy,
+            /* The factor converts into the endpoints' type. */
+            U: Into<T>,
+        {
+            self.0 + (self.1 - self.0) * rhs.into()
+        }
+
+        let lerp = Lerp(2.0, 6.0);
+
+        assert_eq!(4.0, &lerp * 0.5f32);
+        assert_eq!(6.0, &lerp * 1.0f32);
+        assert_eq!(2.0, lerp * 0.0f32);
+
+        #[opimps::impl_ops(Add)]
+        fn add<T>(self: Lerp<T>, rhs: Lerp<T>) -> Lerp<T>
+        where
+            // Both endpoints are added pairwise.
+            T: Add<Output = T>
+                + Copy
+                // Trailing comment after the last bound.
+        {
+            Lerp(self.0 + rhs.0, self.1 + rhs.1)
+        }
+
+        let (a, b) = (Lerp(1, 2), Lerp(3, 4));
+
+        assert_eq!(Lerp(4, 6), &a + &b);
+        assert_eq!(Lerp(4, 6), &a + Lerp(3, 4));
+        assert_eq!(Lerp(4, 6), Lerp(1, 2) + &b);
+        assert_eq!(Lerp(4, 6), a + b);
+    }
+
+    #[test]
+    fn test_unit_struct_operands() {
+        use std::ops::{BitOr, Not};
+
+        // Neither `Copy` nor `Clone`, like a typestate marker that mustn't be duplicated.
+        #[derive(Debug, PartialEq)]
+        struct Flag;
+
+        #[derive(Debug, PartialEq)]
+        struct Cleared;
+
+        #[opimps::impl_ops(BitOr)]
+        fn bitor(self: Flag, rhs: Flag) -> Flag {
+            Flag
+        }
+
+        #[opimps::impl_uni_ops(Not)]
+        fn not(self: Flag) -> Cleared {
+            Cleared
+        }
+
+        assert_eq!(Flag, &Flag | &Flag);
+        assert_eq!(Flag, &Flag | Flag);
+        assert_eq!(Flag, Flag | &Flag);
+        assert_eq!(Flag, Flag | Flag);
+        assert_eq!(Cleared, !&Flag);
+        assert_eq!(Cleared, !Flag);
+    }
+
+    #[test]
+    fn test_impl_iter_sum_non_clone_item() {
+        #[derive(Debug, PartialEq)]
+        struct Tot(i32);
+
+        #[opimps::impl_iter_sum]
+        fn sum<I: Iterator<Item = Tot>>(iter: I) -> Tot {
+            Tot(iter.map(|t| t.0).sum())
+        }
+
+        assert_eq!(Tot(6), vec![Tot(1), Tot(2), Tot(3)].into_iter().sum::<Tot>());
+    }
+
+    #[test]
+    #[allow(clippy::op_ref)] // Comparing borrowed operands is what's being tested.
+    fn test_impl_cmp_ops_generic_rhs() {
+        struct Tagged<T>(T);
+
+        #[opimps::impl_cmp_ops(PartialEq)]
+        fn eq<T: PartialEq>(self: Tagged<T>, rhs: T) -> bool {
+            self.0 == *rhs
+        }
+
+        assert!(Tagged(3) == 3);
+        assert!(Tagged(3) != &4);
+    }
+
+    #[test]
+    fn test_impl_ops_commutative_skip() {
+        use std::ops::Mul;
+
+        #[derive(Debug, PartialEq)]
+        struct Speed(f64);
+        struct Hours(f64);
+
+        #[derive(Debug, PartialEq)]
+        struct Distance(f64);
+
+        #[opimps::impl_ops(Mul; commutative, skip(owned_owned))]
+        fn mul(self: Speed, rhs: Hours) -> Distance {
+            Distance(self.0 * rhs.0)
+        }
+
+        let (speed, hours) = (Speed(30.0), Hours(2.0));
+
+        assert_eq!(Distance(60.0), &speed * &hours);
+        assert_eq!(Distance(60.0), &hours * speed);
+        assert_eq!(Distance(60.0), Hours(2.0) * &Speed(30.0));
+    }
 }